@@ -0,0 +1,9 @@
+use std::env;
+
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(wfwrc_tsan)");
+    let sanitizers = env::var("CARGO_CFG_SANITIZE").unwrap_or_default();
+    if sanitizers.split(',').any(|s| s == "thread") {
+        println!("cargo::rustc-cfg=wfwrc_tsan");
+    }
+}
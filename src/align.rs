@@ -0,0 +1,122 @@
+//! Payloads with a caller-chosen minimum alignment, e.g. for SIMD or `O_DIRECT` buffers.
+//!
+//! The alignment is part of the type, so deallocation always sees the same layout.
+use core::alloc::Layout;
+use core::marker::PhantomData;
+use core::{fmt, ops, ptr};
+
+use crate::{Arc, ArcInner, Counters};
+
+/// A zero-sized marker type with a specific alignment.
+///
+/// # Safety
+///
+/// `ALIGN` must equal the type's alignment, and the type must be zero-sized.
+pub unsafe trait Alignment: Copy + Send + Sync + 'static {
+    const ALIGN: usize;
+}
+
+macro_rules! alignments {
+    ($($name:ident = $align:literal,)*) => {
+        $(
+            #[derive(Debug, Clone, Copy)]
+            #[repr(align($align))]
+            pub struct $name;
+
+            unsafe impl Alignment for $name {
+                const ALIGN: usize = $align;
+            }
+        )*
+    };
+}
+
+alignments! {
+    Align8 = 8,
+    Align16 = 16,
+    Align32 = 32,
+    Align64 = 64,
+    Align128 = 128,
+    Align256 = 256,
+    Align512 = 512,
+    Align1024 = 1024,
+    Align2048 = 2048,
+    Align4096 = 4096,
+}
+
+/// `T` placed at an address aligned to at least `A::ALIGN`.
+#[repr(C)]
+pub struct Aligned<A: Alignment, T: ?Sized> {
+    _align: [A; 0],
+    _marker: PhantomData<A>,
+    value: T,
+}
+
+impl<A: Alignment, T> Aligned<A, T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            _align: [],
+            _marker: PhantomData,
+            value,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<A: Alignment, T: ?Sized> Aligned<A, T> {
+    /// The guaranteed alignment of the value's address.
+    pub const ALIGN: usize = A::ALIGN;
+}
+
+impl<A: Alignment, T: ?Sized> ops::Deref for Aligned<A, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<A: Alignment, T: ?Sized> ops::DerefMut for Aligned<A, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<A: Alignment, T: ?Sized + fmt::Debug> fmt::Debug for Aligned<A, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T> Arc<T> {
+    /// Allocates `value` at an address aligned to at least `A::ALIGN`.
+    pub fn new_aligned<A: Alignment>(value: T) -> Arc<Aligned<A, T>> {
+        Arc::new(Aligned::new(value))
+    }
+}
+
+impl<A: Alignment, T: Copy> Arc<Aligned<A, [T]>> {
+    /// Creates an aligned slice of `n` copies of `elem`.
+    pub fn repeat_aligned(elem: T, n: usize) -> Self {
+        // Same as what `Layout::for_value` computes for the result, since the slice sits at
+        // offset 0 of `Aligned`.
+        let layout = Layout::array::<T>(n)
+            .and_then(|array| Layout::new::<[A; 0]>().extend(array))
+            .and_then(|(payload, _)| Layout::new::<Counters>().extend(payload))
+            .expect("capacity overflow")
+            .0
+            .pad_to_align();
+        unsafe {
+            let ptr = ArcInner::allocate(layout, |mem| {
+                ptr::slice_from_raw_parts_mut(mem, n) as *mut ArcInner<Aligned<A, [T]>>
+            });
+            let elems = ptr::addr_of_mut!((*ptr.as_ptr()).inner).cast::<T>();
+            for i in 0..n {
+                elems.add(i).write(elem);
+            }
+            Self(ptr)
+        }
+    }
+}
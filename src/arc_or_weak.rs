@@ -0,0 +1,97 @@
+use core::{fmt, mem};
+
+use crate::{Arc, Weak};
+
+/// Either a strong or a weak reference, such as an edge in a graph whose direction decides
+/// whether it keeps the target alive.
+pub enum ArcOrWeak<T: ?Sized> {
+    Strong(Arc<T>),
+    Weak(Weak<T>),
+}
+
+impl<T: ?Sized> ArcOrWeak<T> {
+    pub fn is_strong(&self) -> bool {
+        matches!(self, Self::Strong(_))
+    }
+
+    /// Borrows the value if this is a strong reference.
+    pub fn get(&self) -> Option<&T> {
+        match self {
+            Self::Strong(arc) => Some(arc),
+            Self::Weak(_) => None,
+        }
+    }
+
+    /// Returns a strong reference, or `None` if this is weak and the value is gone.
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        match self {
+            Self::Strong(arc) => Some(arc.clone()),
+            Self::Weak(weak) => weak.upgrade(),
+        }
+    }
+
+    pub fn downgrade(&self) -> Weak<T> {
+        match self {
+            Self::Strong(arc) => Arc::downgrade(arc),
+            Self::Weak(weak) => weak.clone(),
+        }
+    }
+}
+
+impl<T> ArcOrWeak<T> {
+    /// Turns this into a weak reference in place, dropping the value if this was its last
+    /// strong reference.
+    pub fn make_weak(&mut self) {
+        if let Self::Strong(_) = self {
+            let Self::Strong(arc) = mem::replace(self, Self::Weak(Weak::new())) else {
+                unreachable!()
+            };
+            *self = Self::Weak(Arc::into_weak(arc));
+        }
+    }
+
+    /// Turns this into a strong reference in place. Returns `false` and stays weak if the value
+    /// is gone.
+    pub fn make_strong(&mut self) -> bool {
+        match self {
+            Self::Strong(_) => true,
+            Self::Weak(weak) => match weak.upgrade() {
+                Some(arc) => {
+                    *self = Self::Strong(arc);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for ArcOrWeak<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Strong(arc) => Self::Strong(arc.clone()),
+            Self::Weak(weak) => Self::Weak(weak.clone()),
+        }
+    }
+}
+
+impl<T: ?Sized> From<Arc<T>> for ArcOrWeak<T> {
+    fn from(arc: Arc<T>) -> Self {
+        Self::Strong(arc)
+    }
+}
+
+impl<T: ?Sized> From<Weak<T>> for ArcOrWeak<T> {
+    fn from(weak: Weak<T>) -> Self {
+        Self::Weak(weak)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArcOrWeak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Strong(arc) => f.debug_tuple("Strong").field(arc).finish(),
+            Self::Weak(weak) => f.debug_tuple("Weak").field(weak).finish(),
+        }
+    }
+}
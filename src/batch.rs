@@ -0,0 +1,44 @@
+use core::mem::{self, ManuallyDrop};
+use core::ptr::NonNull;
+
+use crate::{Arc, ArcInner, Count};
+
+/// Drops all `arcs`, with one atomic decrement per distinct allocation instead of one per
+/// handle.
+///
+/// If dropping a value panics, the remaining references are still released.
+pub fn drop_all<T: ?Sized>(arcs: impl IntoIterator<Item = Arc<T>>) {
+    let mut ptrs = arcs
+        .into_iter()
+        .map(|arc| ManuallyDrop::new(arc).0)
+        .collect::<Vec<_>>();
+    ptrs.sort_unstable_by_key(|ptr| ptr.cast::<u8>());
+
+    struct Release<T: ?Sized>(std::vec::IntoIter<NonNull<ArcInner<T>>>);
+    impl<T: ?Sized> Release<T> {
+        fn next_group(&mut self) -> Option<(NonNull<ArcInner<T>>, Count)> {
+            let ptr = self.0.next()?;
+            let mut n: Count = 1;
+            while let Some(next) = self.0.as_slice().first() {
+                if next.cast::<u8>() != ptr.cast() {
+                    break;
+                }
+                self.0.next();
+                n += 1;
+            }
+            Some((ptr, n))
+        }
+    }
+    impl<T: ?Sized> Drop for Release<T> {
+        fn drop(&mut self) {
+            while let Some((ptr, n)) = self.next_group() {
+                // Should dropping the value panic, the rest is released by this guard.
+                let rest = Release(mem::take(&mut self.0));
+                unsafe { ArcInner::release_strong_many(ptr, n) };
+                self.0 = mem::take(&mut ManuallyDrop::new(rest).0);
+            }
+        }
+    }
+
+    drop(Release(ptrs.into_iter()));
+}
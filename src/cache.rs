@@ -0,0 +1,126 @@
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{Arc, Weak};
+
+/// A concurrent map from keys to shared values, holding the values weakly.
+///
+/// Entries whose values have been dropped are pruned as the map grows. Optionally, the most
+/// recently inserted values are also kept alive, see [`ArcCache::with_pinned`].
+pub struct ArcCache<K, V> {
+    state: Mutex<State<K, V>>,
+}
+
+struct State<K, V> {
+    map: HashMap<K, Weak<V>>,
+    // Map size after the last prune; the next one happens at twice that.
+    pruned_len: usize,
+    pinned: VecDeque<Arc<V>>,
+    pin_capacity: usize,
+}
+
+impl<K, V> ArcCache<K, V> {
+    pub fn new() -> Self {
+        Self::with_pinned(0)
+    }
+
+    /// Creates a cache that additionally keeps the `count` most recently inserted values alive.
+    pub fn with_pinned(count: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                map: HashMap::new(),
+                pruned_len: 0,
+                pinned: VecDeque::with_capacity(count),
+                pin_capacity: count,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<K, V>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Removes entries whose values are gone.
+    pub fn prune(&self) {
+        let mut state = self.lock();
+        // Upgrading here could leave us with the last strong reference, dropping the value
+        // under the lock.
+        state.map.retain(|_, weak| weak.is_alive());
+        state.pruned_len = state.map.len();
+    }
+
+    /// Drops all entries and pinned values.
+    pub fn clear(&self) {
+        let pinned = {
+            let mut state = self.lock();
+            state.map.clear();
+            state.pruned_len = 0;
+            std::mem::take(&mut state.pinned)
+        };
+        drop(pinned);
+    }
+}
+
+impl<K: Hash + Eq, V> ArcCache<K, V> {
+    pub fn get<Q>(&self, key: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().map.get(key)?.upgrade()
+    }
+
+    /// Returns the cached value for `key`, creating it with `f` if it is missing or gone.
+    ///
+    /// `f` runs without holding the cache's lock, so it may use the cache itself. If another
+    /// thread inserts the same key meanwhile, its value wins and the one from `f` is dropped.
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> Arc<V> {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let value = Arc::new(f());
+
+        let mut state = self.lock();
+        if let Some(existing) = state.map.get(&key).and_then(Weak::upgrade) {
+            drop(state);
+            return existing;
+        }
+        if state.map.len() >= state.pruned_len * 2 {
+            state.map.retain(|_, weak| weak.is_alive());
+            state.pruned_len = state.map.len().max(4);
+        }
+        state.map.insert(key, Arc::downgrade(&value));
+        let evicted = if state.pin_capacity == 0 {
+            None
+        } else {
+            let evicted = (state.pinned.len() == state.pin_capacity)
+                .then(|| state.pinned.pop_front())
+                .flatten();
+            state.pinned.push_back(value.clone());
+            evicted
+        };
+        // Drop any evicted value outside the lock.
+        drop(state);
+        drop(evicted);
+        value
+    }
+}
+
+impl<K, V> Default for ArcCache<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> fmt::Debug for ArcCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("ArcCache")
+            .field("entries", &state.map.len())
+            .field("pinned", &state.pinned.len())
+            .finish()
+    }
+}
@@ -0,0 +1,85 @@
+//! Cancellation signalled by dropping guards.
+//!
+//! The guards are the strong references of one [`Arc`] and the tokens are weak ones, so a
+//! token observes cancellation exactly when its upgrade would fail.
+use std::fmt;
+
+use crate::wait_group::{Signal, Wait};
+use crate::{Arc, Weak};
+
+/// Keeps its tokens from being cancelled. Clones share the same tokens, which are cancelled
+/// once the last guard is dropped.
+#[derive(Clone)]
+pub struct DropGuard(Arc<Signal>);
+
+impl DropGuard {
+    pub fn new() -> Self {
+        Self(Arc::new(Signal::new()))
+    }
+
+    /// A token that is cancelled once this guard and all its clones are dropped.
+    pub fn token(&self) -> CancellationToken {
+        CancellationToken(Arc::downgrade(&self.0))
+    }
+
+    /// Gives up this guard, cancelling the tokens if it was the last one.
+    pub fn cancel(self) {}
+}
+
+impl Default for DropGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DropGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DropGuard")
+            .field(
+                "guards",
+                &Arc::strong_count_with(&self.0, core::sync::atomic::Ordering::Relaxed),
+            )
+            .finish()
+    }
+}
+
+/// Observes whether all [`DropGuard`]s it was created from are gone.
+#[derive(Clone)]
+pub struct CancellationToken(Weak<Signal>);
+
+impl CancellationToken {
+    /// A token that is cancelled from the start.
+    pub const fn cancelled_token() -> Self {
+        Self(Weak::new())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        // Briefly becoming a guard is fine: if it turns out to be the last one, dropping it
+        // cancels as it should.
+        self.0.upgrade().is_none()
+    }
+
+    /// Blocks until cancelled.
+    pub fn wait(&self) {
+        self.cancelled().block();
+    }
+
+    /// Returns a future that resolves once cancelled.
+    pub fn cancelled(&self) -> Wait {
+        Wait::new(self.0.clone())
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::cancelled_token()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
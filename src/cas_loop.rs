@@ -0,0 +1,232 @@
+//! The textbook layout, as in `std::sync::Arc`: a strong count and a weak count that holds one
+//! implicit weak reference for all strong ones together.
+//!
+//! Upgrading is a CAS loop that refuses to resurrect a zero strong count, and so is downgrading,
+//! since it has to wait for `is_unique` to release its lock on the weak count. Only meant as a
+//! baseline to compare the default counters against.
+use core::fmt;
+use core::ptr::NonNull;
+
+use crate::{
+    abort, acquire_fence, ArcInner, AtomicCount, Count, DebugState, Ordering, MAX_REFCOUNT,
+};
+
+// Held in the weak count by `is_unique` while it checks the strong count.
+const LOCKED: Count = Count::MAX;
+
+const _: () = assert!(MAX_REFCOUNT < LOCKED);
+
+pub(crate) struct Counters {
+    strong: AtomicCount,
+    weak: AtomicCount,
+    #[cfg(feature = "diagnostics")]
+    pub(crate) diag: crate::diag::DiagSlot,
+}
+
+impl Counters {
+    pub(crate) fn new() -> Self {
+        Self {
+            strong: 1.into(),
+            weak: 1.into(),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    /// Counters of a static allocation that is never released. The strong count starts so high
+    /// that it cannot drop to zero, yet leaves as much room for clones as for releases.
+    #[cfg(not(feature = "loom"))]
+    pub(crate) const fn new_static() -> Self {
+        Self {
+            strong: AtomicCount::new(MAX_REFCOUNT / 2),
+            weak: AtomicCount::new(1),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    /// Counters of an allocation whose value is still being constructed: no strong reference,
+    /// and one weak reference for the constructor to hand out besides the implicit one.
+    pub(crate) fn new_cyclic() -> Self {
+        Self {
+            strong: 0.into(),
+            weak: 2.into(),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    pub(crate) fn debug_fields(&self, f: &mut fmt::DebugStruct<'_, '_>) {
+        f.field("strong", &self.strong.load(Ordering::Relaxed))
+            .field("weak", &self.weak.load(Ordering::Relaxed));
+    }
+}
+
+impl<T: ?Sized> ArcInner<T> {
+    pub(crate) fn acquire_strong_from_strong(&self) {
+        let old = self.counters.strong.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            abort();
+        }
+    }
+
+    pub(crate) fn acquire_strong_from_strong_unchecked(&self) {
+        self.counters.strong.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn acquire_strong_from_weak(&self) -> bool {
+        let strong = &self.counters.strong;
+        let mut cur = strong.load(Ordering::Relaxed);
+        loop {
+            if cur == 0 {
+                return false;
+            }
+            if cur > MAX_REFCOUNT {
+                abort();
+            }
+            match strong.compare_exchange_weak(cur, cur + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return true,
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    /// Opens an allocation created with `Counters::new_cyclic` with a single strong reference.
+    pub(crate) fn finish_cyclic(&self) {
+        // Upgrades never touch a zero strong count, so there is nothing to overwrite.
+        self.counters.strong.store(1, Ordering::Release);
+    }
+
+    pub(crate) unsafe fn release_strong(this: NonNull<Self>) {
+        Self::release_strong_many(this, 1);
+    }
+
+    /// Releases `n` strong references at once.
+    pub(crate) unsafe fn release_strong_many(this: NonNull<Self>, n: Count) {
+        Self::release_strong_many_with(this, n, |inner| inner.drop_inner());
+    }
+
+    /// Like `release_strong_many`, but calls `last` instead of dropping the value.
+    pub(crate) unsafe fn release_strong_many_with(
+        mut this: NonNull<Self>,
+        n: Count,
+        last: impl FnOnce(&mut Self),
+    ) {
+        let strong = &this.as_ref().counters.strong;
+        if strong.fetch_sub(n, Ordering::Release) != n {
+            return;
+        }
+        acquire_fence(strong);
+        last(this.as_mut());
+        Self::release_weak(this);
+    }
+
+    /// Converts a strong reference into a weak one.
+    pub(crate) unsafe fn release_strong_into_weak(mut this: NonNull<Self>) {
+        if !this.as_ref().try_close() {
+            this.as_ref().acquire_weak_from_strong();
+            Self::release_strong(this);
+            return;
+        }
+        // The implicit weak reference becomes the returned one.
+        #[cfg(feature = "diagnostics")]
+        this.as_ref().counters.diag.record_drop();
+        this.as_mut().drop_inner();
+    }
+
+    pub(crate) fn strong_count(&self, order: core::sync::atomic::Ordering) -> usize {
+        crate::count_to_usize(self.counters.strong.load(order))
+    }
+
+    /// The number of weak references, excluding the implicit one, or zero once the value is
+    /// gone.
+    pub(crate) fn weak_count(&self, order: core::sync::atomic::Ordering) -> usize {
+        let weak = self.counters.weak.load(order);
+        if self.counters.strong.load(order) == 0 || weak == LOCKED {
+            return 0;
+        }
+        crate::count_to_usize(weak - 1)
+    }
+
+    /// Whether weak references besides the implicit one exist, once closed.
+    #[cfg(feature = "diagnostics")]
+    pub(crate) fn weak_remains(&self) -> bool {
+        self.counters.weak.load(Ordering::Relaxed) > 1
+    }
+
+    pub(crate) fn debug_state(&self) -> DebugState {
+        let strong = self.counters.strong.load(Ordering::Relaxed);
+        DebugState {
+            strong: crate::count_to_usize(strong),
+            weak: crate::count_to_usize(self.counters.weak.load(Ordering::Relaxed)),
+            weak_exist: false,
+            closed: strong == 0,
+        }
+    }
+
+    /// Whether this is the only reference, strong or weak.
+    pub(crate) fn is_unique(&self) -> bool {
+        let weak = &self.counters.weak;
+        // Lock out downgrades while checking the strong count, otherwise another strong
+        // reference could downgrade and drop in between.
+        if weak
+            .compare_exchange(1, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        let unique = self.counters.strong.load(Ordering::Acquire) == 1;
+        weak.store(1, Ordering::Release);
+        unique
+    }
+
+    /// Gives up the last strong reference without dropping the value, so the caller can take it
+    /// over. Returns `false` if other strong references exist.
+    ///
+    /// After success, the caller must eventually call `release_closed`.
+    pub(crate) unsafe fn try_close(&self) -> bool {
+        self.counters
+            .strong
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    pub(crate) unsafe fn release_closed(this: NonNull<Self>) {
+        Self::release_weak(this);
+    }
+
+    pub(crate) fn acquire_weak_from_strong(&self) {
+        let weak = &self.counters.weak;
+        let mut cur = weak.load(Ordering::Relaxed);
+        loop {
+            if cur == LOCKED {
+                crate::spin_loop();
+                cur = weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if cur > MAX_REFCOUNT {
+                abort();
+            }
+            match weak.compare_exchange_weak(cur, cur + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return,
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    pub(crate) fn acquire_weak_from_weak(&self) {
+        // An existing weak reference keeps the count above one, so it cannot be locked.
+        let old = self.counters.weak.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            abort();
+        }
+    }
+
+    pub(crate) unsafe fn release_weak(this: NonNull<Self>) {
+        let weak = &this.as_ref().counters.weak;
+        if weak.fetch_sub(1, Ordering::Release) == 1 {
+            acquire_fence(weak);
+            Self::dealloc(this);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::Arc;
+
+impl<T: ?Sized + PartialEq> PartialEq for Arc<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for Arc<T> {}
+
+impl<T: ?Sized + PartialEq> PartialEq<T> for Arc<T> {
+    fn eq(&self, other: &T) -> bool {
+        **self == *other
+    }
+}
+
+impl PartialEq<&str> for Arc<str> {
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: PartialEq> PartialEq<&[T]> for Arc<[T]> {
+    fn eq(&self, other: &&[T]) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq<[T; N]> for Arc<[T]> {
+    fn eq(&self, other: &[T; N]) -> bool {
+        **self == *other
+    }
+}
+
+impl PartialEq<Arc<str>> for str {
+    fn eq(&self, other: &Arc<str>) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<Arc<str>> for &str {
+    fn eq(&self, other: &Arc<str>) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: PartialEq> PartialEq<Arc<[T]>> for [T] {
+    fn eq(&self, other: &Arc<[T]>) -> bool {
+        *self == **other
+    }
+}
+
+impl<T: PartialEq> PartialEq<Arc<[T]>> for &[T] {
+    fn eq(&self, other: &Arc<[T]>) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq<Arc<[T]>> for [T; N] {
+    fn eq(&self, other: &Arc<[T]>) -> bool {
+        *self == **other
+    }
+}
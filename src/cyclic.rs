@@ -0,0 +1,62 @@
+use core::alloc::Layout;
+use core::mem::ManuallyDrop;
+use core::ptr;
+
+use crate::{Arc, ArcInner, Counters, Weak};
+
+/// An allocation whose value is still being constructed. Its weak reference can be handed out,
+/// but does not upgrade until `finish`. Dropping it instead releases the allocation once all
+/// handed out weak references are gone.
+pub(crate) struct Pending<T> {
+    weak: Weak<T>,
+}
+
+impl<T> Pending<T> {
+    pub(crate) fn new() -> Self {
+        let ptr = unsafe { ArcInner::allocate(Layout::new::<ArcInner<T>>(), |mem| mem.cast()) };
+        unsafe { (*ptr.as_ptr()).counters = Counters::new_cyclic() };
+        Self { weak: Weak(ptr) }
+    }
+
+    pub(crate) fn weak(&self) -> &Weak<T> {
+        &self.weak
+    }
+
+    pub(crate) fn finish(self, value: T) -> Arc<T> {
+        let this = ManuallyDrop::new(self);
+        let weak = unsafe { ptr::read(&this.weak) };
+        let ptr = weak.0;
+        unsafe {
+            ptr::addr_of_mut!((*ptr.as_ptr()).inner).write(ManuallyDrop::new(value));
+            ptr.as_ref().finish_cyclic();
+        }
+        drop(weak);
+        Arc(ptr)
+    }
+}
+
+impl<T> Drop for Pending<T> {
+    fn drop(&mut self) {
+        // Besides the one in `weak`, the counters start with the implicit weak reference.
+        unsafe { ArcInner::release_weak(self.weak.0) }
+    }
+}
+
+impl<T> Arc<T> {
+    /// Creates two values that hold weak references to themselves and to each other, such as
+    /// a doubly-linked pair. Neither `Weak` upgrades until `new_cyclic2` returns.
+    pub fn new_cyclic2<U>(f: impl FnOnce(&Weak<T>, &Weak<U>) -> (T, U)) -> (Self, Arc<U>) {
+        let (first, second) = (Pending::new(), Pending::new());
+        let (a, b) = f(first.weak(), second.weak());
+        (first.finish(a), second.finish(b))
+    }
+
+    /// Creates `N` values that hold weak references to each other, such as a small clique.
+    /// None of the `Weak`s upgrade until `new_cyclic_group` returns.
+    pub fn new_cyclic_group<const N: usize>(f: impl FnOnce(&[Weak<T>; N]) -> [T; N]) -> [Self; N] {
+        let pending: [Pending<T>; N] = core::array::from_fn(|_| Pending::new());
+        let weaks = pending.each_ref().map(|pending| pending.weak().clone());
+        let mut values = f(&weaks).into_iter();
+        pending.map(|pending| pending.finish(values.next().unwrap()))
+    }
+}
@@ -0,0 +1,67 @@
+//! Dropping deeply nested structures without recursing once per level.
+//!
+//! Values that own further `Arc`s, like the nodes of a linked list or tree, release them with
+//! [`Arc::drop_iterative`] in their `Drop` impl. Instead of being dropped right away, they are
+//! queued and dropped one after another by the outermost call on the thread, so the stack
+//! depth no longer grows with the depth of the structure.
+use core::cell::{Cell, RefCell};
+use core::mem::{self, ManuallyDrop};
+use core::ptr::NonNull;
+
+use crate::{Arc, ArcInner};
+
+#[derive(Clone, Copy)]
+struct Deferred {
+    ptr: NonNull<()>,
+    release: unsafe fn(NonNull<()>),
+}
+
+thread_local! {
+    static DRAINING: Cell<bool> = const { Cell::new(false) };
+    static QUEUE: RefCell<Vec<Deferred>> = const { RefCell::new(Vec::new()) };
+}
+
+unsafe fn release<T>(ptr: NonNull<()>) {
+    ArcInner::<T>::release_strong(ptr.cast());
+}
+
+impl<T: 'static> Arc<T> {
+    /// Drops this strong reference, without recursing if this happens while another one is
+    /// dropped this way on the same thread.
+    pub fn drop_iterative(this: Self) {
+        let deferred = Deferred {
+            ptr: ManuallyDrop::new(this).0.cast(),
+            release: release::<T>,
+        };
+        match DRAINING.try_with(|draining| draining.replace(true)) {
+            Ok(false) => {}
+            // An outer call drains the queue.
+            Ok(true)
+                if QUEUE
+                    .try_with(|queue| queue.borrow_mut().push(deferred))
+                    .is_ok() =>
+            {
+                return
+            }
+            // The thread is exiting.
+            _ => return unsafe { (deferred.release)(deferred.ptr) },
+        }
+
+        // Should dropping a value panic, the rest of the queue is still released.
+        struct Drain;
+        impl Drop for Drain {
+            fn drop(&mut self) {
+                while let Ok(Some(d)) = QUEUE.try_with(|queue| queue.borrow_mut().pop()) {
+                    let rest = Drain;
+                    unsafe { (d.release)(d.ptr) };
+                    mem::forget(rest);
+                }
+                let _ = DRAINING.try_with(|draining| draining.set(false));
+            }
+        }
+
+        let drain = Drain;
+        unsafe { (deferred.release)(deferred.ptr) };
+        drop(drain);
+    }
+}
@@ -0,0 +1,211 @@
+//! Records of where values were dropped, to explain failed upgrades, labels to tell
+//! allocations apart, and checks that handles stay on their thread.
+use std::backtrace::Backtrace;
+use std::sync::OnceLock;
+use std::thread::{self, ThreadId};
+use std::time::SystemTime;
+use std::{error, fmt, ops};
+
+use crate::Arc;
+
+/// Per-allocation diagnostics, kept next to the counters.
+pub(crate) struct DiagSlot {
+    drop_record: OnceLock<Box<DropRecord>>,
+    label: Option<&'static str>,
+}
+
+impl DiagSlot {
+    pub(crate) const fn new() -> Self {
+        Self {
+            drop_record: OnceLock::new(),
+            label: None,
+        }
+    }
+
+    pub(crate) fn record_drop(&self) {
+        self.drop_record
+            .get_or_init(|| Box::new(DropRecord::capture()));
+    }
+
+    pub(crate) fn drop_record(&self) -> Option<&DropRecord> {
+        self.drop_record.get().map(|record| &**record)
+    }
+
+    pub(crate) fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    pub(crate) fn set_label(&mut self, label: &'static str) {
+        self.label = Some(label);
+    }
+}
+
+/// When, where and by which thread the value was dropped or taken out by its last strong
+/// reference.
+pub struct DropRecord {
+    thread_id: ThreadId,
+    thread_name: Option<String>,
+    time: SystemTime,
+    backtrace: Backtrace,
+}
+
+impl DropRecord {
+    fn capture() -> Self {
+        let thread = thread::current();
+        Self {
+            thread_id: thread.id(),
+            thread_name: thread.name().map(Into::into),
+            time: SystemTime::now(),
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn thread_id(&self) -> ThreadId {
+        self.thread_id
+    }
+
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// Only captured if enabled through `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`.
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+}
+
+impl fmt::Debug for DropRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DropRecord")
+            .field("thread_id", &self.thread_id)
+            .field("thread_name", &self.thread_name)
+            .field("time", &self.time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for DropRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dropped by thread {:?} ({}) at {:?}",
+            self.thread_id,
+            self.thread_name.as_deref().unwrap_or("<unnamed>"),
+            self.time,
+        )?;
+        if let std::backtrace::BacktraceStatus::Captured = self.backtrace.status() {
+            write!(f, "\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returned by [`Weak::upgrade_diagnostic`](crate::Weak::upgrade_diagnostic).
+#[derive(Debug)]
+pub struct UpgradeError<'a> {
+    pub(crate) record: Option<&'a DropRecord>,
+    pub(crate) label: Option<&'static str>,
+}
+
+impl<'a> UpgradeError<'a> {
+    /// `None` for a `Weak::new()` handle, or a value that was never fully constructed.
+    pub fn record(&self) -> Option<&'a DropRecord> {
+        self.record
+    }
+
+    /// The label given with [`Arc::new_labeled`](crate::Arc::new_labeled), if any.
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+}
+
+impl fmt::Display for UpgradeError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("upgrade failed: ")?;
+        if let Some(label) = self.label {
+            write!(f, "[{label}] ")?;
+        }
+        match self.record {
+            Some(record) => write!(f, "value {record}"),
+            None => f.write_str("no value"),
+        }
+    }
+}
+
+impl error::Error for UpgradeError<'_> {}
+
+/// An [`Arc`] that is meant to stay on the thread that created it, and panics when it is
+/// cloned or dereferenced on another one.
+///
+/// This verifies that a value can switch to a thread-local handle such as
+/// [`HybridRc`](crate::hybrid::HybridRc) before doing so.
+pub struct AffinityChecked<T: ?Sized> {
+    arc: Arc<T>,
+    owner: ThreadId,
+}
+
+impl<T: ?Sized> AffinityChecked<T> {
+    /// Binds `arc` to the current thread.
+    pub fn new(arc: Arc<T>) -> Self {
+        Self {
+            arc,
+            owner: thread::current().id(),
+        }
+    }
+
+    /// The thread that created this handle.
+    pub fn owner(&self) -> ThreadId {
+        self.owner
+    }
+
+    /// Unwraps the `Arc`, without checking the thread.
+    pub fn into_inner(self) -> Arc<T> {
+        self.arc
+    }
+
+    #[track_caller]
+    fn check(&self) {
+        let current = thread::current();
+        if current.id() != self.owner {
+            panic!(
+                "Arc owned by thread {:?} used on thread {:?} ({})",
+                self.owner,
+                current.id(),
+                current.name().unwrap_or("<unnamed>"),
+            );
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for AffinityChecked<T> {
+    #[track_caller]
+    fn clone(&self) -> Self {
+        self.check();
+        Self {
+            arc: self.arc.clone(),
+            owner: self.owner,
+        }
+    }
+}
+
+impl<T: ?Sized> ops::Deref for AffinityChecked<T> {
+    type Target = T;
+
+    #[track_caller]
+    fn deref(&self) -> &T {
+        self.check();
+        &self.arc
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for AffinityChecked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AffinityChecked")
+            .field("owner", &self.owner)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,99 @@
+use core::mem::ManuallyDrop;
+use core::ptr::NonNull;
+use core::{fmt, ops};
+use std::error::Error;
+
+use crate::{Arc, ArcInner};
+
+impl Arc<dyn Error + Send + Sync> {
+    /// Creates a type-erased shared error, standing in for the unsizing coercion that is not
+    /// available on stable.
+    pub fn from_error<E: Error + Send + Sync + 'static>(error: E) -> Self {
+        let this = ManuallyDrop::new(Arc::new(error));
+        let ptr: NonNull<ArcInner<dyn Error + Send + Sync>> = this.0;
+        Arc(ptr)
+    }
+
+    /// Recovers the concrete error type, or gives back the original `Arc` if it is not an `E`.
+    pub fn downcast<E: Error + 'static>(self) -> Result<Arc<E>, Self> {
+        if !(*self).is::<E>() {
+            return Err(self);
+        }
+        let this = ManuallyDrop::new(self);
+        Ok(Arc(this.0.cast()))
+    }
+}
+
+/// A type-erased error that is cheap to clone, for reporting one failure to many callers.
+///
+/// Like other error wrappers with a blanket `From` impl, it does not implement [`Error`]
+/// itself, but dereferences to the wrapped error.
+#[derive(Clone)]
+pub struct SharedError(Arc<dyn Error + Send + Sync>);
+
+impl SharedError {
+    pub fn new<E: Error + Send + Sync + 'static>(error: E) -> Self {
+        Self(Arc::from_error(error))
+    }
+
+    /// The wrapped error, if it is an `E`.
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+}
+
+impl<E: Error + Send + Sync + 'static> From<E> for SharedError {
+    fn from(error: E) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<Arc<dyn Error + Send + Sync>> for SharedError {
+    fn from(error: Arc<dyn Error + Send + Sync>) -> Self {
+        Self(error)
+    }
+}
+
+impl From<SharedError> for Arc<dyn Error + Send + Sync> {
+    fn from(error: SharedError) -> Self {
+        error.0
+    }
+}
+
+impl ops::Deref for SharedError {
+    type Target = dyn Error + Send + Sync;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl AsRef<dyn Error + Send + Sync> for SharedError {
+    fn as_ref(&self) -> &(dyn Error + Send + Sync + 'static) {
+        &*self.0
+    }
+}
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Debug for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+/// Returned by the `try_new` family when the allocator fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+impl Error for AllocError {}
@@ -0,0 +1,54 @@
+use core::fmt::{self, Write};
+use core::ptr::NonNull;
+
+use crate::{Arc, ArcInner, UniqueArc};
+
+impl Arc<str> {
+    /// Formats `args` into a single exactly-sized allocation, by measuring the output before
+    /// writing it. See also [`format_arc!`](crate::format_arc).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a formatting trait implementation returns an error, like `format!`.
+    pub fn from_fmt(args: fmt::Arguments<'_>) -> Self {
+        if let Some(s) = args.as_str() {
+            return Self::from(s);
+        }
+
+        struct Measure(usize);
+        impl Write for Measure {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0 += s.len();
+                Ok(())
+            }
+        }
+
+        struct Fill(UniqueArc<[u8]>);
+        impl Write for Fill {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                if s.len() > self.0.capacity() - self.0.len() {
+                    return Err(fmt::Error);
+                }
+                self.0.extend_from_slice(s.as_bytes());
+                Ok(())
+            }
+        }
+
+        let mut measure = Measure(0);
+        measure
+            .write_fmt(args)
+            .expect("a formatting trait implementation returned an error");
+        if measure.0 == 0 {
+            return Self::from("");
+        }
+        let mut fill = Fill(UniqueArc::with_capacity(measure.0));
+        if fill.write_fmt(args).is_err() || fill.0.len() != measure.0 {
+            // Non-deterministic formatting: fall back to formatting into a `String`.
+            return Self::from(&*std::fmt::format(args));
+        }
+        let bytes = UniqueArc::into_arc(fill.0);
+        // Only whole `str`s were written.
+        let ptr = core::mem::ManuallyDrop::new(bytes).0.as_ptr() as *mut ArcInner<str>;
+        Self(unsafe { NonNull::new_unchecked(ptr) })
+    }
+}
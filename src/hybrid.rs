@@ -0,0 +1,145 @@
+//! Handles that count references without atomics until they are shared with another thread.
+//!
+//! All [`HybridRc`]s of a value on its owning thread together hold a single strong reference to
+//! an [`Arc`], and count among themselves with plain loads and stores. Only promoting to a
+//! [`HybridArc`], which can be sent to other threads, touches the atomic counters.
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use std::thread::{self, ThreadId};
+use std::{fmt, ops};
+
+use crate::Arc;
+
+struct Shared<T> {
+    owner: ThreadId,
+    // Only ever accessed from `owner`, so it needs no read-modify-write operations.
+    local: AtomicUsize,
+    value: T,
+}
+
+/// A reference on the thread that created the value, counted without atomics.
+pub struct HybridRc<T> {
+    arc: ManuallyDrop<Arc<Shared<T>>>,
+    _not_send: PhantomData<*const ()>,
+}
+
+/// A reference that can be sent to other threads, counted atomically.
+pub struct HybridArc<T>(Arc<Shared<T>>);
+
+impl<T> HybridRc<T> {
+    /// Creates a value owned by the current thread.
+    pub fn new(value: T) -> Self {
+        Self {
+            arc: ManuallyDrop::new(Arc::new(Shared {
+                owner: thread::current().id(),
+                local: AtomicUsize::new(1),
+                value,
+            })),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Promotes to a reference that can be sent to other threads.
+    pub fn to_arc(this: &Self) -> HybridArc<T> {
+        HybridArc(Arc::clone(&this.arc))
+    }
+
+    /// The number of references on the owning thread.
+    pub fn local_count(this: &Self) -> usize {
+        this.arc.local.load(Relaxed)
+    }
+
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        std::ptr::eq(&**this.arc, &**other.arc)
+    }
+}
+
+impl<T> Clone for HybridRc<T> {
+    fn clone(&self) -> Self {
+        let local = &self.arc.local;
+        let count = local.load(Relaxed);
+        if count == usize::MAX {
+            std::process::abort();
+        }
+        local.store(count + 1, Relaxed);
+        Self {
+            arc: ManuallyDrop::new(Arc(self.arc.0)),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for HybridRc<T> {
+    fn drop(&mut self) {
+        let local = &self.arc.local;
+        let count = local.load(Relaxed) - 1;
+        local.store(count, Relaxed);
+        if count == 0 {
+            unsafe { ManuallyDrop::drop(&mut self.arc) };
+        }
+    }
+}
+
+impl<T> ops::Deref for HybridRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.arc.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for HybridRc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> HybridArc<T> {
+    /// Converts back into a local reference, or gives back this one if the current thread does
+    /// not own the value.
+    pub fn into_local(self) -> Result<HybridRc<T>, Self> {
+        if self.0.owner != thread::current().id() {
+            return Err(self);
+        }
+        let local = &self.0.local;
+        let count = local.load(Relaxed);
+        if count == usize::MAX {
+            std::process::abort();
+        }
+        local.store(count + 1, Relaxed);
+        // The local references already hold a strong one, unless this is the first again.
+        let arc = match count {
+            0 => ManuallyDrop::new(self.0),
+            _ => ManuallyDrop::new(Arc(self.0 .0)),
+        };
+        Ok(HybridRc {
+            arc,
+            _not_send: PhantomData,
+        })
+    }
+
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        std::ptr::eq(&*this.0, &*other.0)
+    }
+}
+
+impl<T> Clone for HybridArc<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> ops::Deref for HybridArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for HybridArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+
+use crate::Arc;
+
+/// A reader over a shared byte buffer. Cloning is cheap and gives an independent position.
+#[derive(Clone)]
+pub struct ArcCursor {
+    data: Arc<[u8]>,
+    pos: u64,
+}
+
+impl ArcCursor {
+    pub const fn new(data: Arc<[u8]>) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub const fn get_ref(&self) -> &Arc<[u8]> {
+        &self.data
+    }
+
+    pub fn into_inner(self) -> Arc<[u8]> {
+        self.data
+    }
+
+    pub const fn position(&self) -> u64 {
+        self.pos
+    }
+
+    pub const fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    fn remaining(&self) -> &[u8] {
+        let start =
+            usize::try_from(self.pos).map_or(self.data.len(), |pos| pos.min(self.data.len()));
+        &self.data[start..]
+    }
+}
+
+impl fmt::Debug for ArcCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArcCursor")
+            .field("len", &self.data.len())
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
+impl From<Arc<[u8]>> for ArcCursor {
+    fn from(data: Arc<[u8]>) -> Self {
+        Self::new(data)
+    }
+}
+
+impl Read for ArcCursor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.remaining().read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.remaining().read_exact(buf)?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl BufRead for ArcCursor {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl Seek for ArcCursor {
+    fn seek(&mut self, style: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match style {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (self.data.len() as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+#[cfg(any(unix, target_os = "wasi"))]
+mod fd {
+    use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+
+    use crate::Arc;
+
+    impl<T: AsFd + ?Sized> AsFd for Arc<T> {
+        fn as_fd(&self) -> BorrowedFd<'_> {
+            (**self).as_fd()
+        }
+    }
+
+    impl<T: AsRawFd + ?Sized> AsRawFd for Arc<T> {
+        fn as_raw_fd(&self) -> RawFd {
+            (**self).as_raw_fd()
+        }
+    }
+}
+
+#[cfg(windows)]
+mod handle {
+    use std::os::windows::io::{
+        AsHandle, AsRawHandle, AsRawSocket, AsSocket, BorrowedHandle, BorrowedSocket, RawHandle,
+        RawSocket,
+    };
+
+    use crate::Arc;
+
+    impl<T: AsHandle + ?Sized> AsHandle for Arc<T> {
+        fn as_handle(&self) -> BorrowedHandle<'_> {
+            (**self).as_handle()
+        }
+    }
+
+    impl<T: AsRawHandle + ?Sized> AsRawHandle for Arc<T> {
+        fn as_raw_handle(&self) -> RawHandle {
+            (**self).as_raw_handle()
+        }
+    }
+
+    impl<T: AsSocket + ?Sized> AsSocket for Arc<T> {
+        fn as_socket(&self) -> BorrowedSocket<'_> {
+            (**self).as_socket()
+        }
+    }
+
+    impl<T: AsRawSocket + ?Sized> AsRawSocket for Arc<T> {
+        fn as_raw_socket(&self) -> RawSocket {
+            (**self).as_raw_socket()
+        }
+    }
+}
@@ -1,4 +1,18 @@
+//! A wait-free weak `Arc`.
+//!
+//! # Counter limits
+//!
+//! Exceeding the maximum number of strong or weak references aborts the process. By default
+//! that takes about `isize::MAX / 4` strong references, which `compact` lowers to about 2^29.
+//! `packed` splits one word between both counts, which leaves only about 2^15 strong and 2^14
+//! weak references on 32-bit targets, and about 2^31 and 2^30 on 64-bit ones.
+#![cfg_attr(
+    feature = "unstable-coerce",
+    feature(coerce_unsized, dispatch_from_dyn, unsize)
+)]
+
 use core::alloc::Layout;
+use core::convert::Infallible;
 use core::mem::ManuallyDrop;
 use core::ptr::NonNull;
 use core::{fmt, ops, ptr};
@@ -9,43 +23,487 @@ extern crate alloc;
 
 #[cfg(not(feature = "loom"))]
 use {
-    alloc::alloc::{alloc, dealloc},
-    core::sync::atomic::{fence, AtomicUsize, Ordering},
+    alloc::alloc::{alloc, alloc_zeroed, dealloc},
+    core::sync::atomic,
 };
 
 #[cfg(feature = "loom")]
 use loom::{
-    alloc::{alloc, dealloc},
-    sync::atomic::{fence, AtomicUsize, Ordering},
+    alloc::{alloc, alloc_zeroed, dealloc},
+    sync::atomic,
 };
 
+#[cfg(not(feature = "seqcst"))]
+use atomic::Ordering;
+
+// Debugging aid: upgrade every ordering, and thus every fence, to `SeqCst`.
+#[cfg(feature = "seqcst")]
+#[allow(non_snake_case)]
+mod Ordering {
+    pub(crate) use super::atomic::Ordering::{
+        SeqCst as AcqRel, SeqCst as Acquire, SeqCst as Relaxed, SeqCst as Release,
+    };
+}
+
+#[cfg(all(feature = "packed", feature = "compact"))]
+compile_error!("`packed` and `compact` cannot be enabled together");
+#[cfg(all(feature = "lock", any(feature = "packed", feature = "cas-loop")))]
+compile_error!("`lock` needs the default split counters");
+#[cfg(all(feature = "packed", feature = "cas-loop"))]
+compile_error!("`packed` and `cas-loop` cannot be enabled together");
+#[cfg(all(feature = "compact", target_pointer_width = "16"))]
+compile_error!("`compact` would widen the counters on 16-bit targets");
+#[cfg(all(feature = "packed", target_pointer_width = "16"))]
+compile_error!("`packed` leaves too few bits per counter on 16-bit targets");
+
+#[cfg(all(not(feature = "loom"), feature = "compact"))]
+use core::sync::atomic::AtomicU32 as AtomicCount;
+#[cfg(all(not(feature = "loom"), not(feature = "compact")))]
+use core::sync::atomic::AtomicUsize as AtomicCount;
+#[cfg(all(feature = "loom", feature = "compact"))]
+use loom::sync::atomic::AtomicU32 as AtomicCount;
+#[cfg(all(feature = "loom", not(feature = "compact")))]
+use loom::sync::atomic::AtomicUsize as AtomicCount;
+
+#[doc(hidden)]
+pub use macros::__private;
+
+pub use arc_or_weak::ArcOrWeak;
+pub use batch::drop_all;
+pub use cache::ArcCache;
+pub use error::{AllocError, SharedError};
+pub use io::ArcCursor;
+pub use pin::PinWeak;
+pub use project::ProjectedArc;
+pub use shared::SharedPtr;
+pub use singleflight::Singleflight;
+pub use soft::{trim, SoftArc};
+pub use strong::StrongArc;
+pub use sync::{ArcMutex, ArcRwLock, WeakMutex, WeakRwLock};
+pub use unique::UniqueArc;
+
+#[macro_use]
+mod macros;
+
+pub mod align;
+mod arc_or_weak;
+mod batch;
+mod cache;
+pub mod cancel;
+mod cmp;
+mod cyclic;
+mod deep;
+#[cfg(feature = "diagnostics")]
+pub mod diag;
+#[cfg(feature = "epoch")]
+pub mod epoch;
+mod error;
+mod format;
+pub mod hybrid;
+mod io;
+pub mod list;
+#[cfg(feature = "lock")]
+pub mod lock;
+#[cfg(all(unix, feature = "mmap"))]
+pub mod mmap;
+mod pin;
+pub mod pod;
+pub mod pool;
+mod project;
+#[cfg(feature = "raw")]
+pub mod raw;
+pub mod registry;
+#[cfg(all(unix, feature = "mlock"))]
+pub mod secure;
+mod shared;
+mod singleflight;
+pub mod slice;
+mod soft;
+mod strong;
+mod sync;
+pub mod task;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 #[cfg(test)]
 mod tests;
+mod uninit;
+mod unique;
+pub mod wait_group;
+pub mod watch;
+
+// With `compact`, both counters are 32-bit, shrinking the header to 8 bytes on 64-bit targets.
+// Overflow still aborts, just at a lower threshold.
+#[cfg(not(feature = "compact"))]
+type Count = usize;
+#[cfg(feature = "compact")]
+type Count = u32;
+
+const MAX_REFCOUNT: Count = Count::MAX >> 1;
+
+#[allow(clippy::unnecessary_cast)]
+fn count_to_usize(count: Count) -> usize {
+    count as usize
+}
 
-const MAX_REFCOUNT: usize = isize::MAX as usize;
+/// Called right before this crate blocks the current thread, for
+/// `test_util::assert_no_alloc_or_block`.
+fn note_blocking() {
+    #[cfg(any(test, feature = "test-util"))]
+    test_util::record_block();
+}
+
+// ThreadSanitizer does not understand standalone fences, so under it synchronize through an
+// acquire load of the counter that was just released instead. `wfwrc_tsan` is set by the build
+// script when building with `-Zsanitizer=thread`.
+fn acquire_fence(counter: &AtomicCount) {
+    #[cfg(not(wfwrc_tsan))]
+    {
+        let _ = counter;
+        atomic::fence(Ordering::Acquire);
+    }
+    #[cfg(wfwrc_tsan)]
+    counter.load(Ordering::Acquire);
+}
+
+// Lets loom switch threads while spinning.
+#[cfg(not(feature = "packed"))]
+fn spin_loop() {
+    #[cfg(not(feature = "loom"))]
+    core::hint::spin_loop();
+    #[cfg(feature = "loom")]
+    loom::hint::spin_loop();
+}
+
+// Caller-provided orderings are upgraded as well under `seqcst`.
+#[cfg(not(feature = "seqcst"))]
+fn load_ordering(order: atomic::Ordering) -> atomic::Ordering {
+    order
+}
+#[cfg(feature = "seqcst")]
+fn load_ordering(order: atomic::Ordering) -> atomic::Ordering {
+    assert!(
+        !matches!(order, atomic::Ordering::Release | atomic::Ordering::AcqRel),
+        "there is no such thing as a release load"
+    );
+    atomic::Ordering::SeqCst
+}
+
+const _: () = {
+    assert!(Count::BITS >= 16);
+    // `INVALID_WEAK_ADDR` must not be the start of any real allocation.
+    assert!(core::mem::size_of::<ArcInner<()>>() >= 2);
+};
 
 pub struct Arc<T: ?Sized>(NonNull<ArcInner<T>>);
 
 impl<T> Arc<T> {
     pub fn new(value: T) -> Self {
-        let layout = Layout::new::<ArcInner<T>>();
-        let ptr = unsafe { alloc(layout).cast::<ArcInner<T>>() };
-        let Some(ptr) = NonNull::new(ptr) else {
-            ::alloc::alloc::handle_alloc_error(layout);
-        };
+        match Self::try_new(value) {
+            Ok(this) => this,
+            Err(AllocError) => ::alloc::alloc::handle_alloc_error(Layout::new::<ArcInner<T>>()),
+        }
+    }
+
+    /// Like [`Arc::new`], but returns an error instead of aborting if the allocation fails.
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        let ptr = unsafe { alloc(Layout::new::<ArcInner<T>>()).cast::<ArcInner<T>>() };
+        let ptr = NonNull::new(ptr).ok_or(AllocError)?;
         unsafe { ptr::write(ptr.as_ptr(), ArcInner::new(value)) }
-        Self(ptr)
+        Ok(Self(ptr))
+    }
+
+    /// Creates an `Arc` with a label that shows up in its `Debug` output and in upgrade
+    /// diagnostics, to tell allocations apart.
+    #[cfg(feature = "diagnostics")]
+    pub fn new_labeled(label: &'static str, value: T) -> Self {
+        let this = Self::new(value);
+        unsafe { (*this.0.as_ptr()).counters.diag.set_label(label) };
+        this
+    }
+
+    /// Creates a value that holds weak references to itself. Upgrading the `Weak` passed to `f`
+    /// fails until `new_cyclic` returns.
+    pub fn new_cyclic(f: impl FnOnce(&Weak<T>) -> T) -> Self {
+        match Self::try_new_cyclic(|weak| Ok::<_, Infallible>(f(weak))) {
+            Ok(this) => this,
+            Err(err) => match err {},
+        }
+    }
+
+    /// Like [`Arc::new_cyclic`], but `f` may fail. Weak references that escaped `f` then simply
+    /// never upgrade.
+    pub fn try_new_cyclic<E>(f: impl FnOnce(&Weak<T>) -> Result<T, E>) -> Result<Self, E> {
+        let pending = cyclic::Pending::new();
+        let value = f(pending.weak())?;
+        Ok(pending.finish(value))
+    }
+}
+
+impl<T> Arc<T> {
+    /// Moves the value out if this is the last strong reference, and gives this back otherwise.
+    ///
+    /// Weak references are left behind, never to upgrade again. When several threads race to
+    /// unwrap their clones, all of them may fail; see [`Arc::into_inner`].
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if !unsafe { this.0.as_ref().try_close() } {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        unsafe {
+            this.0.as_ref().record_drop();
+            let value = ManuallyDrop::take(&mut (*this.0.as_ptr()).inner);
+            ArcInner::release_closed(this.0);
+            Ok(value)
+        }
+    }
+
+    /// Moves the value out if this is the last strong reference, and releases it otherwise.
+    ///
+    /// Unlike dropping the result of [`Arc::try_unwrap`], exactly one of several racing callers
+    /// is guaranteed to get the value.
+    pub fn into_inner(this: Self) -> Option<T> {
+        let this = ManuallyDrop::new(this);
+        let mut value = None;
+        unsafe {
+            ArcInner::release_strong_many_with(this.0, 1, |inner| {
+                inner.record_drop();
+                value = Some(ManuallyDrop::take(&mut inner.inner));
+            });
+        }
+        value
+    }
+
+    /// Replaces the value, in place if this is the only reference to the allocation and with a
+    /// fresh allocation otherwise. Other handles keep seeing the old value.
+    pub fn set(this: &mut Self, value: T) {
+        if unsafe { this.0.as_ref().is_unique() } {
+            unsafe { *this.0.as_mut().inner = value };
+        } else {
+            *this = Self::new(value);
+        }
+    }
+
+    /// Like [`Arc::set`] with a value computed from the current one.
+    pub fn update(this: &mut Self, f: impl FnOnce(&T) -> T) {
+        let value = f(this);
+        Self::set(this, value);
+    }
+
+    /// Gives mutable access to the value, cloning it into a fresh allocation first unless this
+    /// is the only reference to the allocation.
+    ///
+    /// If this is the last strong reference, the value is moved rather than cloned, and weak
+    /// references are left behind with the old allocation, never to upgrade again.
+    pub fn make_mut(this: &mut Self) -> &mut T
+    where
+        T: Clone,
+    {
+        if !unsafe { this.0.as_ref().is_unique() } {
+            match Self::try_unwrap(Self(this.0)) {
+                // The strong reference of `this` went with the value.
+                Ok(value) => unsafe { ptr::write(this, Self::new(value)) },
+                Err(alias) => {
+                    let alias = ManuallyDrop::new(alias);
+                    *this = Self::new(T::clone(&alias));
+                }
+            }
+        }
+        unsafe { &mut this.0.as_mut().inner }
+    }
+
+    /// Moves the value out if this is the last strong reference, and clones it otherwise.
+    pub fn unwrap_or_clone(this: Self) -> T
+    where
+        T: Clone,
+    {
+        Self::try_unwrap(this).unwrap_or_else(|this| T::clone(&this))
     }
 }
 
 unsafe impl<T: Send + Sync + ?Sized> Send for Arc<T> {}
 unsafe impl<T: Send + Sync + ?Sized> Sync for Arc<T> {}
 
+#[cfg(feature = "unstable-coerce")]
+impl<T: ?Sized + core::marker::Unsize<U>, U: ?Sized> ops::CoerceUnsized<Arc<U>> for Arc<T> {}
+#[cfg(feature = "unstable-coerce")]
+impl<T: ?Sized + core::marker::Unsize<U>, U: ?Sized> ops::DispatchFromDyn<Arc<U>> for Arc<T> {}
+
 impl<T: ?Sized> Arc<T> {
+    /// Like `clone`, but without the overflow check on the strong count.
+    ///
+    /// # Safety
+    ///
+    /// The number of strong references to this allocation must never exceed the threshold at
+    /// which `clone` would abort, which is `isize::MAX / 4` by default.
+    pub unsafe fn clone_unchecked(this: &Self) -> Self {
+        this.0.as_ref().acquire_strong_from_strong_unchecked();
+        Self(this.0)
+    }
+
+    /// Gives mutable access to the value if this is the only reference to the allocation.
+    ///
+    /// Like [`std::sync::Arc::get_mut`], this succeeds exactly when there are no other strong
+    /// or weak references, including after weak references have been dropped again.
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if unsafe { this.0.as_ref().is_unique() } {
+            Some(unsafe { &mut this.0.as_mut().inner })
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the `Arc`, returning a pointer to the value that keeps its strong reference.
+    pub fn into_raw(this: Self) -> *const T {
+        let ptr = Self::as_ptr(&this);
+        core::mem::forget(this);
+        ptr
+    }
+
+    /// A pointer to the value, without affecting the counts.
+    pub fn as_ptr(this: &Self) -> *const T {
+        unsafe { ptr::addr_of!((*this.0.as_ptr()).inner) as *const T }
+    }
+
+    /// Reclaims the strong reference given up by [`Arc::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `Arc::into_raw` on an `Arc<T>`, and each call must be balanced by
+    /// one to `into_raw` or [`Arc::increment_strong_count`].
+    pub unsafe fn from_raw(ptr: *const T) -> Self {
+        Self(ArcInner::from_value_ptr(
+            ptr,
+            core::mem::align_of_val(&*ptr),
+        ))
+    }
+
+    /// Acquires another strong reference through a pointer from [`Arc::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `Arc::into_raw`, and its strong reference must still be held.
+    pub unsafe fn increment_strong_count(ptr: *const T) {
+        let this = ManuallyDrop::new(Self::from_raw(ptr));
+        let _ = ManuallyDrop::new(Self::clone(&this));
+    }
+
+    /// Releases a strong reference through a pointer from [`Arc::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `Arc::into_raw`, and the caller must own the strong reference
+    /// released.
+    pub unsafe fn decrement_strong_count(ptr: *const T) {
+        drop(Self::from_raw(ptr));
+    }
+
+    /// Projects to a part of the value, or gives back this `Arc` if `f` returns `None`. See
+    /// [`ProjectedArc::try_map`].
+    pub fn try_map<U: ?Sized>(
+        this: Self,
+        f: impl FnOnce(&T) -> Option<&U>,
+    ) -> Result<ProjectedArc<U>, Self>
+    where
+        T: Sized + Send + Sync + 'static,
+    {
+        ProjectedArc::try_map(this, f)
+    }
+
+    /// The layout of the whole allocation, including the counters.
+    pub fn allocation_layout(this: &Self) -> Layout {
+        Layout::for_value(unsafe { this.0.as_ref() })
+    }
+
+    /// Number of heap bytes owned by the allocation, shared by all handles to it. This is zero
+    /// for empty slices and strings that share a static header.
+    pub fn heap_size(this: &Self) -> usize {
+        if slice::is_static_empty(this.0.as_ptr().cast()) {
+            return 0;
+        }
+        Self::allocation_layout(this).size()
+    }
+
+    /// Whether both point to the same allocation, comparing addresses and ignoring any
+    /// metadata.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        ptr::addr_eq(this.0.as_ptr(), other.0.as_ptr())
+    }
+
+    /// The number of strong references, or zero once the value is gone.
+    pub fn strong_count(this: &Self) -> usize {
+        Self::strong_count_with(this, core::sync::atomic::Ordering::Acquire)
+    }
+
+    /// The number of weak references, not counting the one all strong references share.
+    pub fn weak_count(this: &Self) -> usize {
+        Self::weak_count_with(this, core::sync::atomic::Ordering::Acquire)
+    }
+
+    /// Whether there are no other strong or weak references, checked the same way as
+    /// [`Arc::get_mut`] does.
+    ///
+    /// Unless the caller has exclusive access to this `Arc`, other threads may create new
+    /// references right after it returns.
+    pub fn is_unique(this: &Self) -> bool {
+        unsafe { this.0.as_ref().is_unique() }
+    }
+
+    /// The number of strong references, read with the given ordering, for algorithms that
+    /// synchronize through the counts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`, like an atomic load.
+    pub fn strong_count_with(this: &Self, order: core::sync::atomic::Ordering) -> usize {
+        unsafe { this.0.as_ref() }.strong_count(load_ordering(order))
+    }
+
+    /// The number of weak references, read with the given ordering.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is `Release` or `AcqRel`, like an atomic load.
+    pub fn weak_count_with(this: &Self, order: core::sync::atomic::Ordering) -> usize {
+        unsafe { this.0.as_ref() }.weak_count(load_ordering(order))
+    }
+
+    /// The label given with [`Arc::new_labeled`], if any.
+    #[cfg(feature = "diagnostics")]
+    pub fn label(this: &Self) -> Option<&'static str> {
+        unsafe { this.0.as_ref() }.counters.diag.label()
+    }
+
+    /// Decodes the raw counters, for assertions, fuzzers and bug reports.
+    pub fn debug_state(this: &Self) -> DebugState {
+        unsafe { this.0.as_ref() }.debug_state()
+    }
+
+    /// Asserts that there are no other strong or weak references, for catching unexpected
+    /// sharing in tests.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the decoded counters if [`Arc::is_unique`] returns `false`.
+    #[track_caller]
+    pub fn assert_unique(this: &Self) {
+        if !Self::is_unique(this) {
+            panic!("Arc is not unique: {:?}", Self::debug_state(this));
+        }
+    }
+
     pub fn downgrade(this: &Self) -> Weak<T> {
         unsafe { this.0.as_ref().acquire_weak_from_strong() }
         Weak(this.0)
     }
+
+    /// Converts into a weak reference, dropping the value if this was the last strong one.
+    ///
+    /// Equivalent to `Arc::downgrade` followed by `drop`, but cheaper.
+    pub fn into_weak(this: Self) -> Weak<T> {
+        let this = ManuallyDrop::new(this);
+        unsafe { ArcInner::release_strong_into_weak(this.0) };
+        Weak(this.0)
+    }
 }
 
 impl<T: ?Sized> ops::Deref for Arc<T> {
@@ -71,31 +529,78 @@ impl<T: ?Sized> Clone for Arc<T> {
         }
         Self(self.0)
     }
+
+    fn clone_from(&mut self, source: &Self) {
+        if !ptr::addr_eq(self.0.as_ptr(), source.0.as_ptr()) {
+            *self = source.clone();
+        }
+    }
 }
 
-impl<T: fmt::Debug> fmt::Debug for Arc<T> {
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Arc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let inner = unsafe { self.0.as_ref() };
-        f.debug_struct("Arc")
-            .field("strong", &inner.strong.load(Ordering::Relaxed))
-            .field("weak", &inner.weak.load(Ordering::Relaxed))
-            .field("inner", &*inner.inner)
-            .finish()
+        let mut f = f.debug_struct("Arc");
+        #[cfg(feature = "diagnostics")]
+        if let Some(label) = inner.counters.diag.label() {
+            f.field("label", &label);
+        }
+        inner.counters.debug_fields(&mut f);
+        f.field("inner", &&*inner.inner).finish()
     }
 }
 
+/// A relaxed snapshot of the counters of an allocation, decoded from their raw encoding,
+/// which depends on the enabled features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DebugState {
+    /// The number of strong references. Garbage once `closed`, as failed upgrades keep adding
+    /// to it.
+    pub strong: usize,
+    /// The raw weak count. With split counters, this includes the implicit weak reference held
+    /// by all strong ones together once any weak reference was created. With `cas-loop`
+    /// counters, it includes it from the start.
+    pub weak: usize,
+    /// Whether a weak reference was ever created. Always `false` with `packed` or `cas-loop`
+    /// counters.
+    pub weak_exist: bool,
+    /// Whether the value has been dropped or taken out.
+    pub closed: bool,
+}
+
 pub struct Weak<T: ?Sized>(NonNull<ArcInner<T>>);
 
 unsafe impl<T: Send + Sync + ?Sized> Send for Weak<T> {}
 unsafe impl<T: Send + Sync + ?Sized> Sync for Weak<T> {}
 
+// A dangling `Weak` keeps its sentinel address when coerced, so it stays dangling.
+#[cfg(feature = "unstable-coerce")]
+impl<T: ?Sized + core::marker::Unsize<U>, U: ?Sized> ops::CoerceUnsized<Weak<U>> for Weak<T> {}
+#[cfg(feature = "unstable-coerce")]
+impl<T: ?Sized + core::marker::Unsize<U>, U: ?Sized> ops::DispatchFromDyn<Weak<U>> for Weak<T> {}
+
+impl<T: ?Sized> fmt::Pointer for Arc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&Self::as_ptr(self), f)
+    }
+}
+
+impl<T: ?Sized> fmt::Pointer for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+
 impl<T> fmt::Debug for Weak<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("Weak")
     }
 }
 
-const INVALID_WEAK_ADDR: usize = 1;
+// Alignment of `ArcInner` can be 1 on targets like AVR, so use the one address that cannot
+// start an object of at least two bytes.
+const INVALID_WEAK_ADDR: usize = usize::MAX;
 
 impl<T: ?Sized> Drop for Weak<T> {
     fn drop(&mut self) {
@@ -116,18 +621,95 @@ impl<T: ?Sized> Clone for Weak<T> {
         }
         Self(self.0)
     }
+
+    fn clone_from(&mut self, source: &Self) {
+        if !ptr::addr_eq(self.0.as_ptr(), source.0.as_ptr()) {
+            *self = source.clone();
+        }
+    }
 }
 
 impl<T> Weak<T> {
+    #[allow(clippy::new_without_default)]
     pub const fn new() -> Self {
         let ptr = unsafe { NonNull::new_unchecked(INVALID_WEAK_ADDR as *mut _) };
         Self(ptr)
     }
 }
 
+// Only for sized values, whose alignment is known without a live value to ask.
+impl<T> Weak<T> {
+    /// Consumes the `Weak`, returning a pointer to the value that keeps its weak reference.
+    /// The pointer is dangling if the value is gone or if this is from [`Weak::new`].
+    pub fn into_raw(self) -> *const T {
+        let ptr = self.as_ptr();
+        core::mem::forget(self);
+        ptr
+    }
+
+    /// Reclaims the weak reference given up by [`Weak::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `Weak::into_raw` on a `Weak<T>`, and each call must be balanced by
+    /// one to `into_raw`.
+    pub unsafe fn from_raw(ptr: *const T) -> Self {
+        if ptr as usize == INVALID_WEAK_ADDR {
+            return Self::new();
+        }
+        Self(ArcInner::from_value_ptr(ptr, core::mem::align_of::<T>()))
+    }
+}
+
 impl<T: ?Sized> Weak<T> {
-    fn is_dangling(&self) -> bool {
-        self.0.as_ptr().cast::<u8>() as usize == INVALID_WEAK_ADDR
+    /// Whether this was created by [`Weak::new`] rather than from an [`Arc`].
+    pub const fn is_dangling(&self) -> bool {
+        // Pointer-to-integer casts are not allowed in const eval, but wrapping the sentinel
+        // around to null is.
+        const _: () = assert!(INVALID_WEAK_ADDR.wrapping_add(1) == 0);
+        self.0.as_ptr().cast::<u8>().wrapping_add(1).is_null()
+    }
+
+    /// Whether both point to the same allocation, or are both dangling.
+    ///
+    /// Not `const`, since comparing pointers is not possible in const eval.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        ptr::addr_eq(self.0.as_ptr(), other.0.as_ptr())
+    }
+
+    /// A pointer to the value, without affecting the counts. See [`Weak::into_raw`].
+    pub fn as_ptr(&self) -> *const T {
+        if self.is_dangling() {
+            return self.0.as_ptr() as *const T;
+        }
+        unsafe { ptr::addr_of!((*self.0.as_ptr()).inner) as *const T }
+    }
+
+    /// Whether the value is still alive, without upgrading. Only a snapshot, unless the caller
+    /// knows that no upgrade can race with it.
+    pub(crate) fn is_alive(&self) -> bool {
+        !self.is_dangling() && unsafe { self.0.as_ref() }.strong_count(Ordering::Relaxed) != 0
+    }
+
+    /// Decodes the raw counters, or returns `None` if this is dangling.
+    pub fn debug_state(&self) -> Option<DebugState> {
+        (!self.is_dangling()).then(|| unsafe { self.0.as_ref() }.debug_state())
+    }
+
+    /// Like [`Weak::upgrade`], but on failure reports where the value was dropped.
+    #[cfg(feature = "diagnostics")]
+    pub fn upgrade_diagnostic(&self) -> Result<Arc<T>, diag::UpgradeError<'_>> {
+        self.upgrade().ok_or_else(|| {
+            // Dereferencing a dangling pointer must not be evaluated eagerly.
+            let slot = match self.is_dangling() {
+                true => None,
+                false => Some(&unsafe { self.0.as_ref() }.counters.diag),
+            };
+            diag::UpgradeError {
+                record: slot.and_then(|slot| slot.drop_record()),
+                label: slot.and_then(|slot| slot.label()),
+            }
+        })
     }
 
     pub fn upgrade(&self) -> Option<Arc<T>> {
@@ -140,24 +722,65 @@ impl<T: ?Sized> Weak<T> {
             None
         }
     }
-}
 
-struct ArcInner<T: ?Sized> {
-    strong: AtomicUsize,
-    weak: AtomicUsize,
-    inner: ManuallyDrop<T>,
+    /// Upgrades, or falls back to a fresh `Arc` returned by `f` if the value is gone.
+    pub fn upgrade_or_else(&self, f: impl FnOnce() -> Arc<T>) -> Arc<T> {
+        self.upgrade().unwrap_or_else(f)
+    }
+
+    /// Upgrades, or falls back to a new `Arc` of the default value if the value is gone.
+    pub fn upgrade_or_default(&self) -> Arc<T>
+    where
+        T: Default,
+    {
+        self.upgrade_or_else(|| Arc::new(T::default()))
+    }
+
+    /// Upgrades without checking whether the value is still alive, which saves a branch and
+    /// lets the increment be relaxed.
+    ///
+    /// # Safety
+    ///
+    /// Some strong reference to the allocation must exist for the duration of the call, for
+    /// example because one is held under a lock that also guards the last drop.
+    pub unsafe fn upgrade_unchecked(&self) -> Arc<T> {
+        self.0.as_ref().acquire_strong_from_strong();
+        Arc(self.0)
+    }
 }
 
-const WEAK_EXIST: usize = 1;
-const CLOSED: usize = 2;
-const SINGLE_STRONG: usize = 4;
-const SINGLE_WEAK: usize = 1;
+#[cfg(not(any(feature = "packed", feature = "cas-loop")))]
+mod split;
+#[cfg(not(any(feature = "packed", feature = "cas-loop")))]
+use split::Counters;
+
+#[cfg(feature = "packed")]
+mod packed;
+#[cfg(feature = "packed")]
+use packed::Counters;
+
+#[cfg(feature = "cas-loop")]
+mod cas_loop;
+#[cfg(feature = "cas-loop")]
+use cas_loop::Counters;
+
+// Kept in its own module so that `raw` can re-export it without making it public here.
+mod inner {
+    use super::{Counters, ManuallyDrop};
+
+    /// The allocation behind an [`Arc`](crate::Arc): the counters followed by the value.
+    #[repr(C)]
+    pub struct ArcInner<T: ?Sized> {
+        pub(crate) counters: Counters,
+        pub(crate) inner: ManuallyDrop<T>,
+    }
+}
+use inner::ArcInner;
 
 impl<T> ArcInner<T> {
     fn new(inner: T) -> Self {
         Self {
-            strong: SINGLE_STRONG.into(),
-            weak: 0.into(),
+            counters: Counters::new(),
             inner: ManuallyDrop::new(inner),
         }
     }
@@ -165,85 +788,68 @@ impl<T> ArcInner<T> {
 
 impl<T: ?Sized> ArcInner<T> {
     unsafe fn drop_inner(&mut self) {
+        self.record_drop();
         ManuallyDrop::drop(&mut self.inner);
     }
 
-    unsafe fn dealloc(this: NonNull<Self>) {
-        let layout = Layout::for_value(this.as_ref());
-        dealloc(this.as_ptr().cast(), layout);
-    }
-
-    fn acquire_strong_from_strong(&self) {
-        let old = self.strong.fetch_add(SINGLE_STRONG, Ordering::Relaxed);
-        if old > MAX_REFCOUNT {
-            abort();
-        }
+    /// Recovers the allocation from a pointer to its value, which is aligned to `align`.
+    unsafe fn from_value_ptr(value: *const T, align: usize) -> NonNull<Self> {
+        let value_layout = Layout::from_size_align_unchecked(0, align);
+        let (_, offset) = Layout::new::<Counters>().extend(value_layout).unwrap();
+        NonNull::new_unchecked(value.byte_sub(offset) as *mut Self)
     }
 
-    fn acquire_strong_from_weak(&self) -> bool {
-        let old = self.strong.fetch_add(SINGLE_STRONG, Ordering::Acquire);
-        if old > MAX_REFCOUNT {
-            abort();
-        }
-        if old & CLOSED != 0 {
-            return false;
-        }
-        if old < SINGLE_STRONG {
-            debug_assert_eq!(old, WEAK_EXIST);
-            let old_weak = self.weak.fetch_add(SINGLE_WEAK, Ordering::Relaxed);
-            if old_weak > MAX_REFCOUNT {
-                abort();
-            }
+    /// Called when the last strong reference gives up the value. Only weak references can
+    /// look at the record, so it is skipped if there are none left.
+    fn record_drop(&self) {
+        #[cfg(feature = "diagnostics")]
+        if self.weak_remains() {
+            self.counters.diag.record_drop();
         }
-        true
     }
 
-    unsafe fn release_strong(mut this: NonNull<Self>) {
-        let this_ref = this.as_ref();
-        let old = this_ref.strong.fetch_sub(SINGLE_STRONG, Ordering::Release);
-        if old > SINGLE_STRONG + WEAK_EXIST {
-            return;
-        }
-        if old & WEAK_EXIST == 0 {
-            fence(Ordering::Acquire);
-            this.as_mut().drop_inner();
-            Self::dealloc(this);
-            return;
-        }
-        if this_ref
-            .strong
-            .compare_exchange(WEAK_EXIST, CLOSED, Ordering::AcqRel, Ordering::Relaxed)
-            .is_ok()
-        {
-            this.as_mut().drop_inner();
-        }
-        Self::release_weak(this);
+    unsafe fn dealloc(this: NonNull<Self>) {
+        let layout = Layout::for_value(this.as_ref());
+        // With `diagnostics`, the counters own the drop record.
+        ptr::drop_in_place(ptr::addr_of_mut!((*this.as_ptr()).counters));
+        dealloc(this.as_ptr().cast(), layout);
     }
 
-    fn acquire_weak_from_strong(&self) {
-        if self.weak.load(Ordering::Relaxed) == 0
-            && self
-                .weak
-                .compare_exchange(0, SINGLE_WEAK * 2, Ordering::Relaxed, Ordering::Relaxed)
-                .is_ok()
-        {
-            self.strong.fetch_add(WEAK_EXIST, Ordering::Relaxed);
-            return;
-        }
-        self.acquire_weak_from_weak();
+    /// Allocates memory for `layout` and initializes the counters, leaving the value
+    /// uninitialized. `with_meta` attaches pointer metadata to the allocated address.
+    unsafe fn allocate(
+        layout: Layout,
+        with_meta: impl FnOnce(*mut u8) -> *mut Self,
+    ) -> NonNull<Self> {
+        Self::try_allocate(layout, false, with_meta)
+            .unwrap_or_else(|AllocError| ::alloc::alloc::handle_alloc_error(layout))
     }
 
-    fn acquire_weak_from_weak(&self) {
-        let old = self.weak.fetch_add(SINGLE_WEAK, Ordering::Relaxed);
-        if old > MAX_REFCOUNT {
-            abort();
-        }
+    /// Like `allocate`, but with the value zeroed.
+    unsafe fn allocate_zeroed(
+        layout: Layout,
+        with_meta: impl FnOnce(*mut u8) -> *mut Self,
+    ) -> NonNull<Self> {
+        Self::try_allocate(layout, true, with_meta)
+            .unwrap_or_else(|AllocError| ::alloc::alloc::handle_alloc_error(layout))
     }
 
-    unsafe fn release_weak(this: NonNull<Self>) {
-        if this.as_ref().weak.fetch_sub(SINGLE_WEAK, Ordering::Relaxed) == SINGLE_WEAK {
-            fence(Ordering::Acquire);
-            Self::dealloc(this);
+    /// Like `allocate`, but returns an error instead of aborting if the allocation fails.
+    unsafe fn try_allocate(
+        layout: Layout,
+        zeroed: bool,
+        with_meta: impl FnOnce(*mut u8) -> *mut Self,
+    ) -> Result<NonNull<Self>, AllocError> {
+        let mem = if zeroed {
+            alloc_zeroed(layout)
+        } else {
+            alloc(layout)
+        };
+        if mem.is_null() {
+            return Err(AllocError);
         }
+        let ptr = NonNull::new_unchecked(with_meta(mem));
+        ptr::addr_of_mut!((*ptr.as_ptr()).counters).write(Counters::new());
+        Ok(ptr)
     }
 }
@@ -0,0 +1,330 @@
+//! A doubly-linked list of nodes owned elsewhere, for LRU queues and observer chains.
+//!
+//! The list only holds its nodes weakly, and a node unlinks itself when its last strong
+//! reference is dropped. Each node owns a separate link cell, which the list links together
+//! with weak references. A dying node's cell stays reachable until the node has unlinked it, so
+//! traversals simply skip the node instead of losing the rest of the chain.
+use std::fmt;
+use std::ops;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{Arc, Weak};
+
+/// A value that can be linked into a [`WeakList`].
+pub struct Node<T> {
+    link: Arc<Link<T>>,
+    value: T,
+}
+
+struct Link<T> {
+    node: Weak<Node<T>>,
+    state: Mutex<LinkState<T>>,
+}
+
+// Only changed with the lock of the list it points to held.
+struct LinkState<T> {
+    // Dangling if unlinked.
+    list: Weak<Shared<T>>,
+    prev: Weak<Link<T>>,
+    next: Weak<Link<T>>,
+}
+
+impl<T> Default for LinkState<T> {
+    fn default() -> Self {
+        Self {
+            list: Weak::new(),
+            prev: Weak::new(),
+            next: Weak::new(),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T> Node<T> {
+    pub fn new(value: T) -> Arc<Self> {
+        Arc::new_cyclic(|node| Self {
+            link: Arc::new(Link {
+                node: node.clone(),
+                state: Mutex::new(LinkState::default()),
+            }),
+            value,
+        })
+    }
+
+    /// Whether this node is in a list.
+    pub fn is_linked(&self) -> bool {
+        lock(&self.link.state).list.upgrade().is_some()
+    }
+
+    /// The next live node in the same list.
+    pub fn next(&self) -> Option<Arc<Self>> {
+        self.neighbour(|state| &state.next)
+    }
+
+    /// The previous live node in the same list.
+    pub fn prev(&self) -> Option<Arc<Self>> {
+        self.neighbour(|state| &state.prev)
+    }
+
+    fn neighbour(&self, step: impl Fn(&LinkState<T>) -> &Weak<Link<T>>) -> Option<Arc<Self>> {
+        let shared = lock(&self.link.state).list.upgrade()?;
+        let _ends = shared.lock();
+        let mut cur = step(&lock(&self.link.state)).upgrade();
+        while let Some(link) = cur {
+            if let Some(node) = link.node.upgrade() {
+                return Some(node);
+            }
+            cur = step(&lock(&link.state)).upgrade();
+        }
+        None
+    }
+}
+
+impl<T> ops::Deref for Node<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        let Some(shared) = lock(&self.link.state).list.upgrade() else {
+            return;
+        };
+        let mut ends = shared.lock();
+        // The list may have been cleared meanwhile.
+        if contains(&shared, &self.link) {
+            ends.unlink(&self.link);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Node<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Node").field(&self.value).finish()
+    }
+}
+
+/// A list of [`Node`]s, which it holds weakly.
+///
+/// Dropped nodes unlink themselves. The list must not be locked while a node is dropped, so it
+/// never hands out anything that borrows from it; [`WeakList::iter`] takes a snapshot instead.
+pub struct WeakList<T> {
+    shared: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    ends: Mutex<Ends<T>>,
+}
+
+struct Ends<T> {
+    head: Weak<Link<T>>,
+    tail: Weak<Link<T>>,
+    len: usize,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, Ends<T>> {
+        lock(&self.ends)
+    }
+}
+
+/// Whether `link` is in the list `shared`. The caller must hold its lock.
+fn contains<T>(shared: &Arc<Shared<T>>, link: &Link<T>) -> bool {
+    lock(&link.state).list.0 == shared.0
+}
+
+impl<T> Ends<T> {
+    /// Links `link` between `prev` and `next`, which must be adjacent.
+    ///
+    /// Claiming the link happens under its own lock, as the list it may already be in is not
+    /// locked.
+    fn link(
+        &mut self,
+        shared: &Arc<Shared<T>>,
+        link: &Arc<Link<T>>,
+        prev: Weak<Link<T>>,
+        next: Weak<Link<T>>,
+    ) {
+        {
+            let mut state = lock(&link.state);
+            assert!(state.list.upgrade().is_none(), "node is already in a list");
+            *state = LinkState {
+                list: Arc::downgrade(shared),
+                prev: prev.clone(),
+                next: next.clone(),
+            };
+        }
+        match prev.upgrade() {
+            Some(prev) => lock(&prev.state).next = Arc::downgrade(link),
+            None => self.head = Arc::downgrade(link),
+        }
+        match next.upgrade() {
+            Some(next) => lock(&next.state).prev = Arc::downgrade(link),
+            None => self.tail = Arc::downgrade(link),
+        }
+        self.len += 1;
+    }
+
+    fn unlink(&mut self, link: &Link<T>) {
+        let LinkState { prev, next, .. } = std::mem::take(&mut *lock(&link.state));
+        match prev.upgrade() {
+            Some(prev) => lock(&prev.state).next = next.clone(),
+            None => self.head = next.clone(),
+        }
+        match next.upgrade() {
+            Some(next) => lock(&next.state).prev = prev,
+            None => self.tail = prev,
+        }
+        self.len -= 1;
+    }
+}
+
+impl<T> WeakList<T> {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                ends: Mutex::new(Ends {
+                    head: Weak::new(),
+                    tail: Weak::new(),
+                    len: 0,
+                }),
+            }),
+        }
+    }
+
+    /// The number of linked nodes, including dropped ones that have yet to unlink themselves.
+    pub fn len(&self) -> usize {
+        self.shared.lock().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Links `node` at the front.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` is already in a list.
+    pub fn push_front(&self, node: &Arc<Node<T>>) {
+        let mut ends = self.shared.lock();
+        let head = ends.head.clone();
+        ends.link(&self.shared, &node.link, Weak::new(), head);
+    }
+
+    /// Links `node` at the back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` is already in a list.
+    pub fn push_back(&self, node: &Arc<Node<T>>) {
+        let mut ends = self.shared.lock();
+        let tail = ends.tail.clone();
+        ends.link(&self.shared, &node.link, tail, Weak::new());
+    }
+
+    /// Links `node` right after `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is not in this list, or if `node` is already in a list.
+    pub fn insert_after(&self, at: &Node<T>, node: &Arc<Node<T>>) {
+        let mut ends = self.shared.lock();
+        assert!(contains(&self.shared, &at.link), "`at` is not in this list");
+        let next = lock(&at.link.state).next.clone();
+        ends.link(&self.shared, &node.link, Arc::downgrade(&at.link), next);
+    }
+
+    /// Links `node` right before `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is not in this list, or if `node` is already in a list.
+    pub fn insert_before(&self, at: &Node<T>, node: &Arc<Node<T>>) {
+        let mut ends = self.shared.lock();
+        assert!(contains(&self.shared, &at.link), "`at` is not in this list");
+        let prev = lock(&at.link.state).prev.clone();
+        ends.link(&self.shared, &node.link, prev, Arc::downgrade(&at.link));
+    }
+
+    /// Unlinks `node`. Returns `false` if it was not in this list.
+    pub fn remove(&self, node: &Node<T>) -> bool {
+        let mut ends = self.shared.lock();
+        let contained = contains(&self.shared, &node.link);
+        if contained {
+            ends.unlink(&node.link);
+        }
+        contained
+    }
+
+    /// The first live node.
+    pub fn front(&self) -> Option<Arc<Node<T>>> {
+        self.live(|ends| &ends.head, |state| &state.next).next()
+    }
+
+    /// The last live node.
+    pub fn back(&self) -> Option<Arc<Node<T>>> {
+        self.live(|ends| &ends.tail, |state| &state.prev).next()
+    }
+
+    /// A snapshot of the live nodes, front to back.
+    pub fn iter(&self) -> std::vec::IntoIter<Arc<Node<T>>> {
+        self.live(|ends| &ends.head, |state| &state.next)
+    }
+
+    // Collects everything before releasing the lock and dropping the strong references, which
+    // may be the last ones and unlink their nodes.
+    fn live(
+        &self,
+        start: impl Fn(&Ends<T>) -> &Weak<Link<T>>,
+        step: impl Fn(&LinkState<T>) -> &Weak<Link<T>>,
+    ) -> std::vec::IntoIter<Arc<Node<T>>> {
+        let ends = self.shared.lock();
+        let mut nodes = Vec::new();
+        let mut cur = start(&ends).upgrade();
+        while let Some(link) = cur {
+            nodes.extend(link.node.upgrade());
+            cur = step(&lock(&link.state)).upgrade();
+        }
+        drop(ends);
+        nodes.into_iter()
+    }
+
+    /// Unlinks all nodes.
+    pub fn clear(&self) {
+        let mut ends = self.shared.lock();
+        let mut cur = ends.head.upgrade();
+        while let Some(link) = cur {
+            cur = std::mem::take(&mut *lock(&link.state)).next.upgrade();
+        }
+        ends.head = Weak::new();
+        ends.tail = Weak::new();
+        ends.len = 0;
+    }
+}
+
+impl<T> Default for WeakList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for WeakList<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> fmt::Debug for WeakList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeakList")
+            .field("len", &self.len())
+            .finish()
+    }
+}
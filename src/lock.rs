@@ -0,0 +1,147 @@
+//! A mutex integrated into the counters of an [`Arc`], so that `Arc<Locked<T>>` takes no more
+//! space than `Arc<T>`.
+//!
+//! The lock spins briefly, then parks on one of a few global condition variables picked by
+//! address, like `parking_lot`.
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::{fmt, ops};
+use std::sync::{Condvar, Mutex, PoisonError};
+
+use crate::{Arc, Counters};
+
+/// A value that can only be accessed through [`Arc::lock`] and [`Arc::try_lock`].
+pub struct Locked<T: ?Sized>(UnsafeCell<T>);
+
+unsafe impl<T: ?Sized + Send> Send for Locked<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Locked<T> {}
+
+impl<T> Locked<T> {
+    pub const fn new(value: T) -> Self {
+        Self(UnsafeCell::new(value))
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T: ?Sized> Locked<T> {
+    /// Accesses the value without locking, which the exclusive borrow makes unnecessary.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut()
+    }
+}
+
+impl<T: Default> Default for Locked<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Locked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Locked { .. }")
+    }
+}
+
+struct Bucket {
+    mutex: Mutex<()>,
+    condvar: Condvar,
+}
+
+const BUCKETS: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_BUCKET: Bucket = Bucket {
+    mutex: Mutex::new(()),
+    condvar: Condvar::new(),
+};
+
+static PARKING: [Bucket; BUCKETS] = [EMPTY_BUCKET; BUCKETS];
+
+fn bucket(counters: &Counters) -> &'static Bucket {
+    let addr = counters as *const Counters as usize;
+    &PARKING[(addr / core::mem::align_of::<Counters>()) % BUCKETS]
+}
+
+const SPINS: usize = 100;
+
+impl<T: ?Sized> Arc<Locked<T>> {
+    /// Locks the value, blocking until it is available.
+    ///
+    /// Like [`std::sync::Mutex`], locking again from the thread holding the lock deadlocks.
+    pub fn lock(this: &Self) -> LockGuard<'_, T> {
+        let counters = &unsafe { this.0.as_ref() }.counters;
+        for _ in 0..SPINS {
+            if let Some(guard) = Self::try_lock(this) {
+                return guard;
+            }
+            core::hint::spin_loop();
+        }
+        let bucket = bucket(counters);
+        loop {
+            if let Some(guard) = Self::try_lock(this) {
+                return guard;
+            }
+            let parked = bucket.mutex.lock().unwrap_or_else(PoisonError::into_inner);
+            // The unlocking thread takes the bucket mutex before notifying, so it cannot slip
+            // in between marking and waiting.
+            if counters.mark_parked() {
+                crate::note_blocking();
+                drop(bucket.condvar.wait(parked));
+            }
+        }
+    }
+
+    /// Locks the value if it is not already locked.
+    pub fn try_lock(this: &Self) -> Option<LockGuard<'_, T>> {
+        unsafe { this.0.as_ref() }
+            .counters
+            .try_lock()
+            .then(|| LockGuard {
+                arc: this,
+                _not_send: PhantomData,
+            })
+    }
+}
+
+/// Exclusive access to the value of an `Arc<Locked<T>>`, released on drop.
+pub struct LockGuard<'a, T: ?Sized> {
+    arc: &'a Arc<Locked<T>>,
+    // Like `MutexGuard`, since some platforms require unlocking on the locking thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for LockGuard<'_, T> {}
+
+impl<T: ?Sized> ops::Deref for LockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*(**self.arc).0.get() }
+    }
+}
+
+impl<T: ?Sized> ops::DerefMut for LockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *(**self.arc).0.get() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for LockGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Drop for LockGuard<'_, T> {
+    fn drop(&mut self) {
+        let counters = &unsafe { self.arc.0.as_ref() }.counters;
+        if counters.unlock() {
+            let bucket = bucket(counters);
+            let _parked = bucket.mutex.lock().unwrap_or_else(PoisonError::into_inner);
+            bucket.condvar.notify_all();
+        }
+    }
+}
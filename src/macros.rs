@@ -0,0 +1,60 @@
+/// Creates an `Arc<[T]>` from a list of elements, or an `Arc<str>` from a string literal, in a
+/// single allocation.
+///
+/// - `arc![a, b, c]` creates a slice, like `vec!`.
+/// - `arc![elem; n]` creates a slice of `n` clones of `elem`.
+/// - `arc!["text"]` creates a string, while any other lone element creates a slice.
+#[macro_export]
+macro_rules! arc {
+    () => {
+        <$crate::Arc<[_]> as ::core::default::Default>::default()
+    };
+    ($s:literal) => {{
+        #[allow(unused_imports)]
+        use $crate::__private::LoneElement as _;
+        $crate::__private::Lone($s).into_arc()
+    }};
+    ($elem:expr; $n:expr) => {
+        $crate::Arc::<[_]>::from_elem($elem, $n)
+    };
+    ($($elem:expr),+ $(,)?) => {
+        <$crate::Arc<[_]> as ::core::convert::From<_>>::from([$($elem),+])
+    };
+}
+
+/// Like `format!`, but creates an `Arc<str>` directly, without an intermediate `String`.
+#[macro_export]
+macro_rules! format_arc {
+    ($($arg:tt)*) => {
+        $crate::Arc::<str>::from_fmt(::core::format_args!($($arg)*))
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    use crate::Arc;
+
+    /// A lone literal in `arc!`. String literals pick the inherent `into_arc`, which takes
+    /// precedence over the one of [`LoneElement`] that all other literals fall back to.
+    pub struct Lone<T>(pub T);
+
+    impl Lone<&'static str> {
+        pub fn into_arc(self) -> Arc<str> {
+            Arc::from(self.0)
+        }
+    }
+
+    pub trait LoneElement {
+        type Output;
+
+        fn into_arc(self) -> Self::Output;
+    }
+
+    impl<T> LoneElement for Lone<T> {
+        type Output = Arc<[T]>;
+
+        fn into_arc(self) -> Arc<[T]> {
+            Arc::from([self.0])
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Read-only memory-mapped files as shared byte buffers.
+use std::fs::File;
+use std::os::fd::AsRawFd;
+use std::ptr::NonNull;
+use std::{fmt, io, ops, slice};
+
+use crate::Arc;
+
+/// A read-only mapping of a whole file, unmapped on drop.
+pub struct Mmap {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+/// A mapped file shared between threads. The file is unmapped with the last strong reference.
+pub type ArcMmap = Arc<Mmap>;
+
+impl Mmap {
+    /// Maps the whole file read-only.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the mapping is alive, which would
+    /// change or invalidate memory behind a shared reference.
+    pub unsafe fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))?;
+        // `mmap` rejects empty mappings.
+        if len == 0 {
+            return Ok(Self {
+                ptr: NonNull::dangling(),
+                len,
+            });
+        }
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: NonNull::new_unchecked(ptr.cast()),
+            len,
+        })
+    }
+}
+
+impl Arc<Mmap> {
+    /// Maps the whole file read-only into a shared buffer.
+    ///
+    /// # Safety
+    ///
+    /// See [`Mmap::map`].
+    pub unsafe fn map_file(file: &File) -> io::Result<Self> {
+        Mmap::map(file).map(Arc::new)
+    }
+}
+
+impl ops::Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl AsRef<[u8]> for Mmap {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.len) };
+        }
+    }
+}
+
+impl fmt::Debug for Mmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mmap")
+            .field("ptr", &self.ptr)
+            .field("len", &self.len)
+            .finish()
+    }
+}
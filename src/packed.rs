@@ -0,0 +1,277 @@
+//! Both counters packed into a single word: `[strong | weak | CLOSED]`.
+//!
+//! Every acquire, including a resurrecting upgrade, is a single RMW. The price is that releasing
+//! a strong reference is a CAS loop, so it is only lock-free rather than wait-free.
+use core::fmt;
+use core::ptr::NonNull;
+
+use crate::{
+    abort, acquire_fence, ArcInner, AtomicCount, Count, DebugState, Ordering, MAX_REFCOUNT,
+};
+
+const CLOSED: Count = 1;
+const SINGLE_WEAK: Count = 2;
+const SINGLE_STRONG: Count = 1 << (Count::BITS / 2);
+const WEAK_MASK: Count = SINGLE_STRONG - SINGLE_WEAK;
+const MAX_WEAK: Count = WEAK_MASK >> 1;
+
+const _: () = {
+    assert!(WEAK_MASK & CLOSED == 0 && WEAK_MASK & SINGLE_STRONG == 0);
+    assert!(MAX_REFCOUNT / SINGLE_STRONG >= 4096 && MAX_WEAK / SINGLE_WEAK >= 4096);
+};
+
+pub(crate) struct Counters {
+    state: AtomicCount,
+    #[cfg(feature = "diagnostics")]
+    pub(crate) diag: crate::diag::DiagSlot,
+}
+
+impl Counters {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: SINGLE_STRONG.into(),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    /// Counters of a static allocation that is never released. The strong count starts so high
+    /// that it cannot drop to zero, yet leaves as much room for clones as for releases.
+    #[cfg(not(feature = "loom"))]
+    pub(crate) const fn new_static() -> Self {
+        Self {
+            state: AtomicCount::new(MAX_REFCOUNT / 2 / SINGLE_STRONG * SINGLE_STRONG),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    /// Counters of an allocation whose value is still being constructed: closed, with one weak
+    /// reference for the constructor to hand out besides the implicit one.
+    pub(crate) fn new_cyclic() -> Self {
+        Self {
+            state: (CLOSED + SINGLE_WEAK * 2).into(),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    pub(crate) fn debug_fields(&self, f: &mut fmt::DebugStruct<'_, '_>) {
+        f.field("state", &self.state.load(Ordering::Relaxed));
+    }
+}
+
+impl<T: ?Sized> ArcInner<T> {
+    pub(crate) fn acquire_strong_from_strong(&self) {
+        let old = self
+            .counters
+            .state
+            .fetch_add(SINGLE_STRONG, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            abort();
+        }
+    }
+
+    pub(crate) fn acquire_strong_from_strong_unchecked(&self) {
+        self.counters
+            .state
+            .fetch_add(SINGLE_STRONG, Ordering::Relaxed);
+    }
+
+    pub(crate) fn acquire_strong_from_weak(&self) -> bool {
+        let old = self
+            .counters
+            .state
+            .fetch_add(SINGLE_STRONG, Ordering::Acquire);
+        // The strong half of a closed state is garbage and allowed to wrap out of the word.
+        if old & CLOSED != 0 {
+            return false;
+        }
+        if old > MAX_REFCOUNT {
+            abort();
+        }
+        // The strong count only reaches zero together with `CLOSED`, so there is no
+        // resurrection to take care of here.
+        debug_assert!(old >= SINGLE_STRONG);
+        true
+    }
+
+    /// Opens an allocation created with `Counters::new_cyclic` with a single strong reference,
+    /// which takes over the implicit weak reference.
+    pub(crate) fn finish_cyclic(&self) {
+        let state = &self.counters.state;
+        let mut old = state.load(Ordering::Relaxed);
+        loop {
+            // Failed upgrades only ever add garbage to the strong half, so it can be overwritten.
+            let new = (old & WEAK_MASK) - SINGLE_WEAK + SINGLE_STRONG;
+            match state.compare_exchange_weak(old, new, Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => return,
+                Err(cur) => old = cur,
+            }
+        }
+    }
+
+    pub(crate) unsafe fn release_strong(this: NonNull<Self>) {
+        Self::release_strong_many(this, 1);
+    }
+
+    /// Releases `n` strong references at once.
+    pub(crate) unsafe fn release_strong_many(this: NonNull<Self>, n: Count) {
+        Self::release_strong_many_with(this, n, |inner| inner.drop_inner());
+    }
+
+    /// Like `release_strong_many`, but calls `last` instead of dropping the value.
+    pub(crate) unsafe fn release_strong_many_with(
+        mut this: NonNull<Self>,
+        n: Count,
+        last: impl FnOnce(&mut Self),
+    ) {
+        let state = &this.as_ref().counters.state;
+        let mut old = state.load(Ordering::Relaxed);
+        loop {
+            let (new, order) = if old >= SINGLE_STRONG * (n + 1) {
+                (old - SINGLE_STRONG * n, Ordering::Release)
+            } else if old & WEAK_MASK == 0 {
+                (CLOSED, Ordering::Acquire)
+            } else {
+                // Close and take an implicit weak reference in one step, so the allocation
+                // outlives the drop below even if all weak references go away meanwhile.
+                (
+                    old - SINGLE_STRONG * n + CLOSED + SINGLE_WEAK,
+                    Ordering::AcqRel,
+                )
+            };
+            if let Err(cur) = state.compare_exchange_weak(old, new, order, Ordering::Relaxed) {
+                old = cur;
+                continue;
+            }
+            if new & CLOSED == 0 {
+                return;
+            }
+            last(this.as_mut());
+            if new & WEAK_MASK == 0 {
+                Self::dealloc(this);
+            } else {
+                Self::release_weak(this);
+            }
+            return;
+        }
+    }
+
+    /// Converts a strong reference into a weak one in a single step.
+    pub(crate) unsafe fn release_strong_into_weak(mut this: NonNull<Self>) {
+        let state = &this.as_ref().counters.state;
+        let mut old = state.load(Ordering::Relaxed);
+        loop {
+            if old & WEAK_MASK > MAX_WEAK {
+                abort();
+            }
+            let (new, order) = if old >= SINGLE_STRONG * 2 {
+                (old - SINGLE_STRONG + SINGLE_WEAK, Ordering::Release)
+            } else {
+                // The added weak reference keeps the allocation alive during the drop below.
+                (old - SINGLE_STRONG + CLOSED + SINGLE_WEAK, Ordering::AcqRel)
+            };
+            match state.compare_exchange_weak(old, new, order, Ordering::Relaxed) {
+                Ok(_) if new & CLOSED != 0 => {
+                    // The returned weak reference is the one taken for closing.
+                    #[cfg(feature = "diagnostics")]
+                    this.as_ref().counters.diag.record_drop();
+                    return this.as_mut().drop_inner();
+                }
+                Ok(_) => return,
+                Err(cur) => old = cur,
+            }
+        }
+    }
+
+    pub(crate) fn strong_count(&self, order: core::sync::atomic::Ordering) -> usize {
+        let state = self.counters.state.load(order);
+        if state & CLOSED != 0 {
+            return 0;
+        }
+        crate::count_to_usize(state / SINGLE_STRONG)
+    }
+
+    /// The number of weak references, or zero once the value is gone.
+    pub(crate) fn weak_count(&self, order: core::sync::atomic::Ordering) -> usize {
+        let state = self.counters.state.load(order);
+        if state & CLOSED != 0 {
+            return 0;
+        }
+        crate::count_to_usize((state & WEAK_MASK) / SINGLE_WEAK)
+    }
+
+    /// Whether weak references besides the one taken for closing exist, once closed.
+    #[cfg(feature = "diagnostics")]
+    pub(crate) fn weak_remains(&self) -> bool {
+        self.counters.state.load(Ordering::Relaxed) & WEAK_MASK > SINGLE_WEAK
+    }
+
+    pub(crate) fn debug_state(&self) -> DebugState {
+        let state = self.counters.state.load(Ordering::Relaxed);
+        DebugState {
+            strong: crate::count_to_usize(state / SINGLE_STRONG),
+            weak: crate::count_to_usize((state & WEAK_MASK) / SINGLE_WEAK),
+            weak_exist: false,
+            closed: state & CLOSED != 0,
+        }
+    }
+
+    /// Whether this is the only reference, strong or weak.
+    pub(crate) fn is_unique(&self) -> bool {
+        self.counters.state.load(Ordering::Acquire) == SINGLE_STRONG
+    }
+
+    /// Gives up the last strong reference without dropping the value, so the caller can take it
+    /// over. Returns `false` if other strong references exist.
+    ///
+    /// After success, the caller must eventually call `release_closed`.
+    pub(crate) unsafe fn try_close(&self) -> bool {
+        let state = &self.counters.state;
+        let mut old = state.load(Ordering::Relaxed);
+        while old < SINGLE_STRONG * 2 {
+            let new = if old & WEAK_MASK == 0 {
+                CLOSED
+            } else {
+                old - SINGLE_STRONG + CLOSED + SINGLE_WEAK
+            };
+            match state.compare_exchange_weak(old, new, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return true,
+                Err(cur) => old = cur,
+            }
+        }
+        false
+    }
+
+    pub(crate) unsafe fn release_closed(this: NonNull<Self>) {
+        if this.as_ref().counters.state.load(Ordering::Relaxed) & WEAK_MASK == 0 {
+            Self::dealloc(this);
+        } else {
+            Self::release_weak(this);
+        }
+    }
+
+    pub(crate) fn acquire_weak_from_strong(&self) {
+        self.acquire_weak_from_weak();
+    }
+
+    pub(crate) fn acquire_weak_from_weak(&self) {
+        let old = self
+            .counters
+            .state
+            .fetch_add(SINGLE_WEAK, Ordering::Relaxed);
+        if old & WEAK_MASK > MAX_WEAK {
+            abort();
+        }
+    }
+
+    pub(crate) unsafe fn release_weak(this: NonNull<Self>) {
+        let state = &this.as_ref().counters.state;
+        let old = state.fetch_sub(SINGLE_WEAK, Ordering::Release);
+        if old & WEAK_MASK == SINGLE_WEAK && old & CLOSED != 0 {
+            acquire_fence(state);
+            Self::dealloc(this);
+        }
+    }
+}
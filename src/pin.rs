@@ -0,0 +1,61 @@
+use core::fmt;
+use core::pin::Pin;
+
+use crate::{Arc, Weak};
+
+impl<T> Arc<T> {
+    /// Creates a pinned `Arc`. The value is never moved out of the allocation, since that
+    /// requires an `Arc` rather than a `Pin<Arc<T>>`.
+    pub fn pin(value: T) -> Pin<Self> {
+        unsafe { Pin::new_unchecked(Self::new(value)) }
+    }
+}
+
+/// A weak reference to a pinned value, which upgrades back into a `Pin<Arc<T>>`.
+pub struct PinWeak<T: ?Sized>(Weak<T>);
+
+impl<T: ?Sized> PinWeak<T> {
+    pub fn downgrade(this: &Pin<Arc<T>>) -> Self {
+        // SAFETY: `Pin` is `repr(transparent)`, and the weak reference can only ever be upgraded
+        // back into a pinned `Arc`.
+        let arc = unsafe { &*(this as *const Pin<Arc<T>>).cast::<Arc<T>>() };
+        Self(Arc::downgrade(arc))
+    }
+
+    pub fn upgrade(&self) -> Option<Pin<Arc<T>>> {
+        // SAFETY: The value was pinned when this was created, and it never moved since then.
+        self.0
+            .upgrade()
+            .map(|arc| unsafe { Pin::new_unchecked(arc) })
+    }
+
+    /// Whether both point to the same allocation, or are both dangling.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+}
+
+impl<T> PinWeak<T> {
+    /// Creates a weak reference that never upgrades.
+    pub const fn new() -> Self {
+        Self(Weak::new())
+    }
+}
+
+impl<T> Default for PinWeak<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized> Clone for PinWeak<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> fmt::Debug for PinWeak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PinWeak")
+    }
+}
@@ -0,0 +1,73 @@
+//! Reinterpreting shared byte buffers as typed data in place.
+use core::mem::{align_of, size_of, ManuallyDrop};
+use core::ptr::{self, NonNull};
+
+use crate::{Arc, ArcInner, Counters};
+
+/// Plain old data: `Copy` types without padding for which every bit pattern is valid.
+///
+/// # Safety
+///
+/// Implementors must have no padding bytes, no invalid bit patterns, and no interior
+/// mutability.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+// The allocation can only be reused if the payload starts at the same offset and the whole
+// layout stays the same, which holds as long as `T` is no more aligned than the counters.
+fn fits_header<T>() -> bool {
+    size_of::<T>() != 0 && align_of::<T>() <= align_of::<Counters>()
+}
+
+impl Arc<[u8]> {
+    /// Reinterprets the bytes as a slice of `T`, sharing the allocation.
+    ///
+    /// Fails if the length is not a multiple of `T`'s size, or if `T` is more aligned than
+    /// the allocation.
+    pub fn try_cast_slice<T: Pod>(this: Self) -> Result<Arc<[T]>, Self> {
+        if !fits_header::<T>() || !this.len().is_multiple_of(size_of::<T>()) {
+            return Err(this);
+        }
+        let len = this.len() / size_of::<T>();
+        let ptr = ManuallyDrop::new(this).0.as_ptr();
+        let ptr = ptr::slice_from_raw_parts_mut(ptr.cast::<T>(), len) as *mut ArcInner<[T]>;
+        Ok(Arc(unsafe { NonNull::new_unchecked(ptr) }))
+    }
+
+    /// Reinterprets the bytes as a single `T`, sharing the allocation.
+    ///
+    /// Fails if the length differs from `T`'s size, or if `T` is more aligned than the
+    /// allocation.
+    pub fn try_cast<T: Pod>(this: Self) -> Result<Arc<T>, Self> {
+        if !fits_header::<T>() || this.len() != size_of::<T>() {
+            return Err(this);
+        }
+        let ptr = ManuallyDrop::new(this).0;
+        Ok(Arc(ptr.cast()))
+    }
+}
+
+impl<T: Pod> Arc<[T]> {
+    /// Reinterprets the slice as its bytes, sharing the allocation.
+    ///
+    /// Fails if `T` is more aligned than the allocation header, since the byte slice would
+    /// then describe a differently aligned allocation.
+    pub fn try_into_bytes(this: Self) -> Result<Arc<[u8]>, Self> {
+        if !fits_header::<T>() {
+            return Err(this);
+        }
+        let len = size_of::<T>() * this.len();
+        let ptr = ManuallyDrop::new(this).0.as_ptr();
+        let ptr = ptr::slice_from_raw_parts_mut(ptr.cast::<u8>(), len) as *mut ArcInner<[u8]>;
+        Ok(Arc(unsafe { NonNull::new_unchecked(ptr) }))
+    }
+}
@@ -0,0 +1,154 @@
+//! Reusing objects such as buffers or connections, instead of dropping them with their last
+//! reference.
+use std::mem::ManuallyDrop;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::{fmt, ops};
+
+use crate::{Arc, Weak};
+
+/// Objects that can be returned to a [`Pool`], after being brought back into a reusable
+/// state.
+pub trait Reset {
+    fn reset(&mut self);
+}
+
+impl<T> Reset for Vec<T> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl Reset for String {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+/// A pool of idle objects. Handles to it are cheap to clone and share the same objects.
+///
+/// Objects are handed out as `Arc<Pooled<T>>`, or as a plain [`Pooled`] to fill before sharing.
+/// When its last reference goes away, the object is reset and returned to the pool, unless the
+/// pool is full or gone, in which case it is dropped.
+pub struct Pool<T> {
+    shared: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    idle: Mutex<Vec<T>>,
+    capacity: usize,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, Vec<T>> {
+        self.idle.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Reset> Pool<T> {
+    /// Creates a pool that keeps at most `capacity` idle objects.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                idle: Mutex::new(Vec::new()),
+                capacity,
+            }),
+        }
+    }
+
+    /// Takes an idle object, or creates one with `f` if there is none, for exclusive use
+    /// before sharing it with `Arc::new`.
+    pub fn take_or_else(&self, f: impl FnOnce() -> T) -> Pooled<T> {
+        let value = self.shared.lock().pop().unwrap_or_else(f);
+        Pooled {
+            value: ManuallyDrop::new(value),
+            pool: Arc::downgrade(&self.shared),
+        }
+    }
+
+    /// Takes an idle object, or creates a default one if there is none.
+    pub fn take(&self) -> Pooled<T>
+    where
+        T: Default,
+    {
+        self.take_or_else(T::default)
+    }
+
+    /// Like [`Pool::take_or_else`], but already shared.
+    pub fn get_or_else(&self, f: impl FnOnce() -> T) -> Arc<Pooled<T>> {
+        Arc::new(self.take_or_else(f))
+    }
+
+    /// Like [`Pool::take`], but already shared.
+    pub fn get(&self) -> Arc<Pooled<T>>
+    where
+        T: Default,
+    {
+        Arc::new(self.take())
+    }
+
+    /// The number of idle objects.
+    pub fn idle(&self) -> usize {
+        self.shared.lock().len()
+    }
+}
+
+impl<T> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("idle", &self.shared.lock().len())
+            .field("capacity", &self.shared.capacity)
+            .finish()
+    }
+}
+
+/// An object borrowed from a [`Pool`], which goes back when dropped.
+pub struct Pooled<T: Reset> {
+    value: ManuallyDrop<T>,
+    pool: Weak<Shared<T>>,
+}
+
+impl<T: Reset> ops::Deref for Pooled<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: Reset> ops::DerefMut for Pooled<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<T: Reset + fmt::Debug> fmt::Debug for Pooled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.value, f)
+    }
+}
+
+impl<T: Reset> Drop for Pooled<T> {
+    fn drop(&mut self) {
+        let mut value = unsafe { ManuallyDrop::take(&mut self.value) };
+        let Some(shared) = self.pool.upgrade() else {
+            return;
+        };
+        // Reset outside of the lock, and only if the object has a chance to be kept.
+        if shared.lock().len() >= shared.capacity {
+            return;
+        }
+        value.reset();
+        let mut idle = shared.lock();
+        if idle.len() < shared.capacity {
+            idle.push(value);
+        }
+    }
+}
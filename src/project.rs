@@ -0,0 +1,147 @@
+use core::mem::ManuallyDrop;
+use core::ptr::{self, NonNull};
+use core::{fmt, ops};
+
+use crate::{Arc, ArcInner};
+
+/// A strong reference to an allocation whose value type has been forgotten. Only the counters,
+/// which come first in every `ArcInner`, are accessed through `ptr`. `release` restores the
+/// type, with `len` as the slice length if there is one, to drop the reference.
+struct Owner {
+    ptr: NonNull<ArcInner<()>>,
+    len: usize,
+    release: unsafe fn(NonNull<ArcInner<()>>, usize),
+}
+
+impl Owner {
+    fn new<T>(arc: Arc<T>) -> Self {
+        unsafe fn release<T>(ptr: NonNull<ArcInner<()>>, _: usize) {
+            drop(Arc(ptr.cast::<ArcInner<T>>()));
+        }
+
+        let arc = ManuallyDrop::new(arc);
+        Self {
+            ptr: arc.0.cast(),
+            len: 0,
+            release: release::<T>,
+        }
+    }
+
+    fn from_slice<T>(arc: Arc<[T]>) -> Self {
+        unsafe fn release<T>(ptr: NonNull<ArcInner<()>>, len: usize) {
+            let ptr = ptr::slice_from_raw_parts_mut(ptr.as_ptr().cast::<T>(), len);
+            drop(Arc(NonNull::new_unchecked(ptr as *mut ArcInner<[T]>)));
+        }
+
+        let arc = ManuallyDrop::new(arc);
+        Self {
+            ptr: arc.0.cast(),
+            len: arc.len(),
+            release: release::<T>,
+        }
+    }
+}
+
+impl Clone for Owner {
+    fn clone(&self) -> Self {
+        unsafe { self.ptr.as_ref() }.acquire_strong_from_strong();
+        Self { ..*self }
+    }
+}
+
+impl Drop for Owner {
+    fn drop(&mut self) {
+        unsafe { (self.release)(self.ptr, self.len) }
+    }
+}
+
+/// A strong reference to a part of the value of an [`Arc`], such as one element of a slice.
+///
+/// It keeps the whole allocation alive, but the type of the owning value is erased, so handles
+/// projected from different owners are interchangeable.
+pub struct ProjectedArc<T: ?Sized> {
+    owner: Owner,
+    ptr: NonNull<T>,
+}
+
+// The owner is dropped wherever the last handle is, so this relies on the constructors only
+// projecting out of owners that are `Send + Sync` whenever `T` is.
+unsafe impl<T: ?Sized + Send + Sync> Send for ProjectedArc<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for ProjectedArc<T> {}
+
+impl<T> ProjectedArc<T> {
+    /// Projects to the element at `index`, which must be in bounds.
+    pub(crate) fn from_element(arc: Arc<[T]>, index: usize) -> Self {
+        assert!(index < arc.len());
+        let ptr = unsafe { NonNull::new_unchecked(ArcInner::elements(arc.0).add(index)) };
+        Self {
+            owner: Owner::from_slice(arc),
+            ptr,
+        }
+    }
+}
+
+impl<T: ?Sized> ProjectedArc<T> {
+    /// Projects to a part of the value, such as an enum variant or an optional field, or gives
+    /// back the original `Arc` if `f` returns `None`.
+    ///
+    /// Since the owner's type is erased, it has to be `Send + Sync` for the projection to be
+    /// sendable, and `'static` so that it cannot outlive anything it borrows.
+    pub fn try_map<O: Send + Sync + 'static>(
+        arc: Arc<O>,
+        f: impl FnOnce(&O) -> Option<&T>,
+    ) -> Result<Self, Arc<O>> {
+        let Some(part) = f(&arc) else {
+            return Err(arc);
+        };
+        let ptr = NonNull::from(part);
+        Ok(Self {
+            owner: Owner::new(arc),
+            ptr,
+        })
+    }
+
+    /// Projects further into a part of this part, keeping the same owner.
+    pub fn try_map_part<U: ?Sized>(
+        this: Self,
+        f: impl FnOnce(&T) -> Option<&U>,
+    ) -> Result<ProjectedArc<U>, Self> {
+        let Some(part) = f(&this) else {
+            return Err(this);
+        };
+        let ptr = NonNull::from(part);
+        Ok(ProjectedArc {
+            owner: this.owner,
+            ptr,
+        })
+    }
+
+    /// Whether both are backed by the same allocation, even if they point to different parts
+    /// of it.
+    pub fn owner_eq(this: &Self, other: &Self) -> bool {
+        this.owner.ptr == other.owner.ptr
+    }
+}
+
+impl<T: ?Sized> ops::Deref for ProjectedArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ?Sized> Clone for ProjectedArc<T> {
+    fn clone(&self) -> Self {
+        Self {
+            owner: self.owner.clone(),
+            ptr: self.ptr,
+        }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ProjectedArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,114 @@
+//! Low-level access to the allocation behind [`Arc`] and [`Weak`], for building other smart
+//! pointers on the same counting protocol.
+//!
+//! This module is exempt from semver: the layout and the protocol may change in any release.
+//! All functions take a pointer to a live allocation created by this crate.
+use core::alloc::Layout;
+use core::mem::ManuallyDrop;
+use core::ptr::{self, NonNull};
+
+pub use crate::inner::ArcInner;
+use crate::{Arc, Counters, Weak};
+
+/// The integer type of the counters.
+pub type Count = crate::Count;
+
+/// Strong and weak counts abort the process beyond this.
+pub const MAX_REFCOUNT: Count = crate::MAX_REFCOUNT;
+
+/// The layout of the counters at the start of every allocation.
+pub const HEADER_LAYOUT: Layout = Layout::new::<Counters>();
+
+/// Allocates memory for `layout` with counters for a single strong reference, leaving the value
+/// uninitialized. `with_meta` attaches pointer metadata to the allocated address.
+///
+/// # Safety
+///
+/// `layout` must be the layout of `ArcInner<T>` for the metadata attached by `with_meta`, and
+/// the value must be initialized before any reference to the allocation is released.
+pub unsafe fn allocate<T: ?Sized>(
+    layout: Layout,
+    with_meta: impl FnOnce(*mut u8) -> *mut ArcInner<T>,
+) -> NonNull<ArcInner<T>> {
+    ArcInner::allocate(layout, with_meta)
+}
+
+/// The location of the value.
+///
+/// # Safety
+///
+/// `this` must point to an allocation created by this crate that has not been freed.
+pub unsafe fn value_ptr<T: ?Sized>(this: NonNull<ArcInner<T>>) -> *mut T {
+    ptr::addr_of_mut!((*this.as_ptr()).inner) as *mut T
+}
+
+pub fn arc_into_ptr<T: ?Sized>(this: Arc<T>) -> NonNull<ArcInner<T>> {
+    ManuallyDrop::new(this).0
+}
+
+/// # Safety
+///
+/// The caller must own a strong reference to `this`, which is transferred to the result.
+pub unsafe fn arc_from_ptr<T: ?Sized>(this: NonNull<ArcInner<T>>) -> Arc<T> {
+    Arc(this)
+}
+
+/// Returns `None` for a dangling weak reference from [`Weak::new`].
+pub fn weak_into_ptr<T: ?Sized>(this: Weak<T>) -> Option<NonNull<ArcInner<T>>> {
+    let this = ManuallyDrop::new(this);
+    (!this.is_dangling()).then_some(this.0)
+}
+
+/// # Safety
+///
+/// The caller must own a weak reference to `this`, which is transferred to the result.
+pub unsafe fn weak_from_ptr<T: ?Sized>(this: NonNull<ArcInner<T>>) -> Weak<T> {
+    Weak(this)
+}
+
+/// # Safety
+///
+/// The caller must hold a strong reference.
+pub unsafe fn acquire_strong_from_strong<T: ?Sized>(this: NonNull<ArcInner<T>>) {
+    this.as_ref().acquire_strong_from_strong();
+}
+
+/// Returns `false` if the value is already dropped, in which case no reference is acquired.
+///
+/// # Safety
+///
+/// The caller must hold a weak reference.
+pub unsafe fn acquire_strong_from_weak<T: ?Sized>(this: NonNull<ArcInner<T>>) -> bool {
+    this.as_ref().acquire_strong_from_weak()
+}
+
+/// Drops the value with the last strong reference, and the allocation with the last reference
+/// overall.
+///
+/// # Safety
+///
+/// The caller must own the strong reference released.
+pub unsafe fn release_strong<T: ?Sized>(this: NonNull<ArcInner<T>>) {
+    ArcInner::release_strong(this);
+}
+
+/// # Safety
+///
+/// The caller must hold a strong reference.
+pub unsafe fn acquire_weak_from_strong<T: ?Sized>(this: NonNull<ArcInner<T>>) {
+    this.as_ref().acquire_weak_from_strong();
+}
+
+/// # Safety
+///
+/// The caller must hold a weak reference.
+pub unsafe fn acquire_weak_from_weak<T: ?Sized>(this: NonNull<ArcInner<T>>) {
+    this.as_ref().acquire_weak_from_weak();
+}
+
+/// # Safety
+///
+/// The caller must own the weak reference released.
+pub unsafe fn release_weak<T: ?Sized>(this: NonNull<ArcInner<T>>) {
+    ArcInner::release_weak(this);
+}
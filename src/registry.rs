@@ -0,0 +1,150 @@
+//! A process-wide registry of shared services, looked up by type or by name.
+//!
+//! Tests can shadow entries for the current thread with [`override_scoped`].
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+use crate::{Arc, ArcInner};
+
+type AnyArc = Arc<dyn Any + Send + Sync>;
+
+impl Arc<dyn Any + Send + Sync> {
+    /// Creates a type-erased shared value, standing in for the unsizing coercion that is not
+    /// available on stable.
+    pub fn from_any<T: Any + Send + Sync>(value: Arc<T>) -> Self {
+        let this = ManuallyDrop::new(value);
+        let ptr: NonNull<ArcInner<dyn Any + Send + Sync>> = this.0;
+        Arc(ptr)
+    }
+
+    /// Recovers the concrete type, or gives back the original `Arc` if it is not a `T`.
+    pub fn downcast<T: Any + Send + Sync>(self) -> Result<Arc<T>, Self> {
+        if !(*self).is::<T>() {
+            return Err(self);
+        }
+        let this = ManuallyDrop::new(self);
+        Ok(Arc(this.0.cast()))
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    types: HashMap<TypeId, AnyArc>,
+    names: HashMap<String, AnyArc>,
+}
+
+fn lock() -> MutexGuard<'static, Entries> {
+    static ENTRIES: OnceLock<Mutex<Entries>> = OnceLock::new();
+    ENTRIES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(PartialEq)]
+enum Key {
+    Type(TypeId),
+    Name(String),
+}
+
+thread_local! {
+    // Innermost last. Overrides are few, so a linear search is fine.
+    static OVERRIDES: RefCell<Vec<(Key, AnyArc)>> = const { RefCell::new(Vec::new()) };
+}
+
+fn overridden(matches: impl Fn(&Key) -> bool) -> Option<AnyArc> {
+    OVERRIDES.with_borrow(|overrides| {
+        let (_, value) = overrides.iter().rev().find(|(key, _)| matches(key))?;
+        Some(value.clone())
+    })
+}
+
+fn downcast<T: Any + Send + Sync>(value: AnyArc) -> Option<Arc<T>> {
+    value.downcast().ok()
+}
+
+/// Registers `value` as the service of type `T`, returning the one it replaces.
+pub fn set<T: Any + Send + Sync>(value: Arc<T>) -> Option<Arc<T>> {
+    let old = lock().types.insert(TypeId::of::<T>(), Arc::from_any(value));
+    old.and_then(downcast)
+}
+
+/// The service of type `T`, if one is registered.
+pub fn get<T: Any + Send + Sync>() -> Option<Arc<T>> {
+    let key = TypeId::of::<T>();
+    let value = overridden(|k| *k == Key::Type(key));
+    let value = value.or_else(|| lock().types.get(&key).cloned());
+    value.and_then(downcast)
+}
+
+/// Unregisters the service of type `T`.
+pub fn remove<T: Any + Send + Sync>() -> Option<Arc<T>> {
+    let old = lock().types.remove(&TypeId::of::<T>());
+    old.and_then(downcast)
+}
+
+/// Registers `value` under `name`, returning the value it replaces.
+pub fn set_named(name: impl Into<String>, value: AnyArc) -> Option<AnyArc> {
+    lock().names.insert(name.into(), value)
+}
+
+/// The value registered under `name`, if there is one and it is a `T`.
+pub fn get_named<T: Any + Send + Sync>(name: &str) -> Option<Arc<T>> {
+    let value = overridden(|k| matches!(k, Key::Name(n) if n == name));
+    let value = value.or_else(|| lock().names.get(name).cloned());
+    value.and_then(downcast)
+}
+
+/// Unregisters the value under `name`.
+pub fn remove_named(name: &str) -> Option<AnyArc> {
+    lock().names.remove(name)
+}
+
+/// Shadows the service of type `T` for the current thread until the returned guard is dropped.
+pub fn override_scoped<T: Any + Send + Sync>(value: Arc<T>) -> Override {
+    push(Key::Type(TypeId::of::<T>()), Arc::from_any(value))
+}
+
+/// Shadows the value under `name` for the current thread until the returned guard is dropped.
+pub fn override_named_scoped(name: impl Into<String>, value: AnyArc) -> Override {
+    push(Key::Name(name.into()), value)
+}
+
+fn push(key: Key, value: AnyArc) -> Override {
+    let depth = OVERRIDES.with_borrow_mut(|overrides| {
+        overrides.push((key, value));
+        overrides.len()
+    });
+    Override {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+/// Undoes an override when dropped. Overrides must be dropped in reverse order of creation.
+#[derive(Debug)]
+#[must_use = "the override ends when the guard is dropped"]
+pub struct Override {
+    depth: usize,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for Override {
+    fn drop(&mut self) {
+        let entry = OVERRIDES.with_borrow_mut(|overrides| {
+            debug_assert_eq!(
+                overrides.len(),
+                self.depth,
+                "overrides dropped out of order"
+            );
+            overrides.truncate(self.depth);
+            overrides.pop()
+        });
+        drop(entry);
+    }
+}
@@ -0,0 +1,103 @@
+//! Secrets kept in locked memory, so that they never reach swap, and zeroed when dropped.
+use std::mem::{align_of, size_of};
+use std::ptr::{self, NonNull};
+use std::{fmt, io, ops};
+
+use crate::Arc;
+
+/// A value in its own locked pages, which are zeroed and unlocked when it is dropped.
+///
+/// Only the pages holding the value are locked. Anything it owns on the heap, and any copies
+/// made while moving it in, are not protected.
+pub struct Locked<T> {
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+unsafe impl<T: Send> Send for Locked<T> {}
+unsafe impl<T: Sync> Sync for Locked<T> {}
+
+/// A secret shared between threads, zeroed with the last strong reference.
+pub type ArcLocked<T> = Arc<Locked<T>>;
+
+impl<T> Locked<T> {
+    /// Moves `value` into freshly mapped pages and locks them.
+    ///
+    /// Fails if the pages cannot be mapped or locked, e.g. because of `RLIMIT_MEMLOCK`, or if
+    /// `T` needs more than page alignment.
+    pub fn new(value: T) -> io::Result<Self> {
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        if align_of::<T>() > page {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "alignment exceeds the page size",
+            ));
+        }
+        // `mmap` rejects empty mappings.
+        let len = size_of::<T>().max(1).next_multiple_of(page);
+        unsafe {
+            let addr = libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            if addr == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::mlock(addr, len) != 0 {
+                let err = io::Error::last_os_error();
+                libc::munmap(addr, len);
+                return Err(err);
+            }
+            let ptr = NonNull::new_unchecked(addr.cast::<T>());
+            ptr.as_ptr().write(value);
+            Ok(Self { ptr, len })
+        }
+    }
+}
+
+impl<T> Arc<Locked<T>> {
+    /// Moves `value` into locked pages and shares it. See [`Locked::new`].
+    pub fn new_locked(value: T) -> io::Result<Self> {
+        Locked::new(value).map(Arc::new)
+    }
+}
+
+impl<T> ops::Deref for Locked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> ops::DerefMut for Locked<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for Locked<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            // Volatile, so that the writes to memory that is about to be unmapped are kept.
+            let bytes = self.ptr.as_ptr().cast::<u8>();
+            for i in 0..size_of::<T>() {
+                bytes.add(i).write_volatile(0);
+            }
+            let addr = self.ptr.as_ptr().cast();
+            libc::munlock(addr, self.len);
+            libc::munmap(addr, self.len);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Locked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Locked").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,78 @@
+use core::ops::Deref;
+
+use crate::{Arc, Weak};
+
+/// Common interface of reference-counted pointers, so data structures can be generic over
+/// this crate's [`Arc`], `std::sync::Arc` and `std::rc::Rc`.
+pub trait SharedPtr<T>: Clone + Deref<Target = T> + Sized {
+    type Weak: Clone;
+
+    fn new(value: T) -> Self;
+
+    /// Returns the value if this is the only strong reference.
+    fn try_unwrap(this: Self) -> Result<T, Self>;
+
+    fn downgrade(this: &Self) -> Self::Weak;
+
+    fn upgrade(weak: &Self::Weak) -> Option<Self>;
+}
+
+impl<T> SharedPtr<T> for Arc<T> {
+    type Weak = Weak<T>;
+
+    fn new(value: T) -> Self {
+        Arc::new(value)
+    }
+
+    fn try_unwrap(this: Self) -> Result<T, Self> {
+        Arc::try_unwrap(this)
+    }
+
+    fn downgrade(this: &Self) -> Self::Weak {
+        Arc::downgrade(this)
+    }
+
+    fn upgrade(weak: &Self::Weak) -> Option<Self> {
+        weak.upgrade()
+    }
+}
+
+impl<T> SharedPtr<T> for std::sync::Arc<T> {
+    type Weak = std::sync::Weak<T>;
+
+    fn new(value: T) -> Self {
+        std::sync::Arc::new(value)
+    }
+
+    fn try_unwrap(this: Self) -> Result<T, Self> {
+        std::sync::Arc::try_unwrap(this)
+    }
+
+    fn downgrade(this: &Self) -> Self::Weak {
+        std::sync::Arc::downgrade(this)
+    }
+
+    fn upgrade(weak: &Self::Weak) -> Option<Self> {
+        weak.upgrade()
+    }
+}
+
+impl<T> SharedPtr<T> for std::rc::Rc<T> {
+    type Weak = std::rc::Weak<T>;
+
+    fn new(value: T) -> Self {
+        std::rc::Rc::new(value)
+    }
+
+    fn try_unwrap(this: Self) -> Result<T, Self> {
+        std::rc::Rc::try_unwrap(this)
+    }
+
+    fn downgrade(this: &Self) -> Self::Weak {
+        std::rc::Rc::downgrade(this)
+    }
+
+    fn upgrade(weak: &Self::Weak) -> Option<Self> {
+        weak.upgrade()
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+use crate::wait_group::{Signal, Wait};
+use crate::{Arc, Weak};
+
+/// Coalesces concurrent computations of the same key, so that callers arriving while one is in
+/// flight wait for it and share its result instead of starting their own.
+///
+/// Results are not cached: once a computation finishes, the next call computes afresh. If it
+/// panics or its future is dropped, one of the waiting callers takes over.
+pub struct Singleflight<K, V> {
+    flights: Mutex<HashMap<K, Arc<Flight<V>>>>,
+}
+
+struct Flight<V> {
+    result: OnceLock<Arc<V>>,
+    // Dropped by the leader once `result` is set or it gave up.
+    done: Weak<Signal>,
+}
+
+/// The caller running the computation. Dropping it ends the flight, with or without a result.
+struct Leader<'a, K: Hash + Eq, V> {
+    flights: &'a Singleflight<K, V>,
+    key: K,
+    flight: Arc<Flight<V>>,
+    _signal: Arc<Signal>,
+}
+
+impl<K: Hash + Eq, V> Leader<'_, K, V> {
+    fn finish(self, value: V) -> Arc<V> {
+        let value = Arc::new(value);
+        let _ = self.flight.result.set(value.clone());
+        value
+    }
+}
+
+impl<K: Hash + Eq, V> Drop for Leader<'_, K, V> {
+    fn drop(&mut self) {
+        // Late callers must start a new flight rather than join one that is over.
+        let flight = self.flights.lock().remove(&self.key);
+        drop(flight);
+    }
+}
+
+impl<K, V> Singleflight<K, V> {
+    pub fn new() -> Self {
+        Self {
+            flights: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, Arc<Flight<V>>>> {
+        self.flights.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Singleflight<K, V> {
+    /// Joins the flight for `key`, or starts one if there is none.
+    fn join(&self, key: &K) -> Result<Arc<Flight<V>>, Leader<'_, K, V>> {
+        let mut flights = self.lock();
+        if let Some(flight) = flights.get(key) {
+            return Ok(flight.clone());
+        }
+        let signal = Arc::new(Signal::new());
+        let flight = Arc::new(Flight {
+            result: OnceLock::new(),
+            done: Arc::downgrade(&signal),
+        });
+        flights.insert(key.clone(), flight.clone());
+        Err(Leader {
+            flights: self,
+            key: key.clone(),
+            flight,
+            _signal: signal,
+        })
+    }
+
+    /// Returns the result of the computation in flight for `key`, or runs `f` to compute it
+    /// while other callers for the same key wait.
+    pub fn get_or_compute(&self, key: K, f: impl FnOnce() -> V) -> Arc<V> {
+        let mut f = Some(f);
+        loop {
+            match self.join(&key) {
+                Ok(flight) => {
+                    Wait::new(flight.done.clone()).block();
+                    if let Some(value) = flight.result.get() {
+                        return value.clone();
+                    }
+                }
+                // Only ever taken once, as a leader never loops.
+                Err(leader) => return leader.finish(f.take().unwrap()()),
+            }
+        }
+    }
+
+    /// Like [`Singleflight::get_or_compute`], but waits and computes asynchronously.
+    pub async fn get_or_compute_async<F: Future<Output = V>>(
+        &self,
+        key: K,
+        f: impl FnOnce() -> F,
+    ) -> Arc<V> {
+        let mut f = Some(f);
+        loop {
+            match self.join(&key) {
+                Ok(flight) => {
+                    Wait::new(flight.done.clone()).await;
+                    if let Some(value) = flight.result.get() {
+                        return value.clone();
+                    }
+                }
+                Err(leader) => {
+                    let value = f.take().unwrap()().await;
+                    return leader.finish(value);
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> Default for Singleflight<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> fmt::Debug for Singleflight<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Singleflight")
+            .field("in_flight", &self.lock().len())
+            .finish()
+    }
+}
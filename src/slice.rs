@@ -0,0 +1,474 @@
+use core::alloc::Layout;
+use core::iter::FusedIterator;
+use core::mem::ManuallyDrop;
+use core::ops::Range;
+use core::ptr::{self, NonNull};
+
+use crate::{Arc, ArcInner, Counters, ProjectedArc, UniqueArc};
+
+// Shared by all empty slices and strings whose element type is not over-aligned, so creating
+// them does not allocate. Each handle owns a strong reference like any other, and the count
+// starts high enough that it never drops to zero, so it is never freed.
+#[cfg(not(feature = "loom"))]
+#[repr(C, align(64))]
+struct EmptyHeader(Counters);
+
+#[cfg(not(feature = "loom"))]
+static EMPTY: EmptyHeader = EmptyHeader(Counters::new_static());
+
+/// Whether `ptr` is the static header shared by empty slices and strings.
+pub(crate) fn is_static_empty(ptr: *const u8) -> bool {
+    #[cfg(not(feature = "loom"))]
+    {
+        ptr::addr_eq(ptr, ptr::addr_of!(EMPTY))
+    }
+    #[cfg(feature = "loom")]
+    {
+        let _ = ptr;
+        false
+    }
+}
+
+impl<T> ArcInner<[T]> {
+    /// An empty slice, pointing at the static header if `T` allows.
+    pub(crate) fn empty() -> NonNull<Self> {
+        #[cfg(not(feature = "loom"))]
+        if core::mem::align_of::<T>() <= core::mem::align_of::<EmptyHeader>() {
+            let mem = ptr::addr_of!(EMPTY).cast_mut().cast::<T>();
+            let ptr = ptr::slice_from_raw_parts_mut(mem, 0) as *mut Self;
+            let ptr = unsafe { NonNull::new_unchecked(ptr) };
+            unsafe { ptr.as_ref() }.acquire_strong_from_strong();
+            return ptr;
+        }
+        Self::allocate_for_slice(0)
+    }
+
+    fn slice_layout(len: usize) -> Layout {
+        Layout::array::<T>(len)
+            .and_then(|array| Layout::new::<Counters>().extend(array))
+            .expect("capacity overflow")
+            .0
+            .pad_to_align()
+    }
+
+    /// Allocates a slice of `len` uninitialized elements with counters of a fresh `Arc`.
+    pub(crate) fn allocate_for_slice(len: usize) -> NonNull<Self> {
+        unsafe {
+            Self::allocate(Self::slice_layout(len), |mem| {
+                ptr::slice_from_raw_parts_mut(mem.cast::<T>(), len) as *mut Self
+            })
+        }
+    }
+
+    /// Like `allocate_for_slice`, but with the elements zeroed.
+    pub(crate) fn allocate_for_slice_zeroed(len: usize) -> NonNull<Self> {
+        unsafe {
+            Self::allocate_zeroed(Self::slice_layout(len), |mem| {
+                ptr::slice_from_raw_parts_mut(mem.cast::<T>(), len) as *mut Self
+            })
+        }
+    }
+
+    /// Reinterprets the allocation as holding `len` elements.
+    pub(crate) fn with_len(this: NonNull<Self>, len: usize) -> NonNull<Self> {
+        let ptr = ptr::slice_from_raw_parts_mut(this.as_ptr().cast::<T>(), len) as *mut Self;
+        unsafe { NonNull::new_unchecked(ptr) }
+    }
+
+    pub(crate) fn elements(this: NonNull<Self>) -> *mut T {
+        unsafe { ptr::addr_of_mut!((*this.as_ptr()).inner).cast::<T>() }
+    }
+}
+
+impl<T> Arc<[T]> {
+    /// Returns an owned handle to the element at `index`, which keeps the whole slice alive.
+    pub fn get_arc(this: &Self, index: usize) -> Option<ProjectedArc<T>> {
+        (index < this.len()).then(|| ProjectedArc::from_element(this.clone(), index))
+    }
+
+    /// Returns an iterator of owned handles to each element, all sharing this allocation.
+    pub fn element_arcs(this: &Self) -> ElementArcs<T> {
+        ElementArcs {
+            range: 0..this.len(),
+            arc: this.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Arc<[T]> {
+    /// Creates a slice of `n` clones of `elem`, like `vec![elem; n]`.
+    pub fn from_elem(elem: T, n: usize) -> Self {
+        if n == 0 {
+            return Self::default();
+        }
+        let mut slice = UniqueArc::with_capacity(n);
+        slice.extend(core::iter::repeat_n(elem, n));
+        UniqueArc::into_arc(slice)
+    }
+}
+
+// There is no spare capacity to grow into, since a capacity field in the header would change
+// the layout every slice is deallocated with. So a unique slice is resized with `realloc`, which
+// the allocator can often do in place, and a shared one is cloned into an exactly-sized copy,
+// leaving it untouched if a clone panics.
+impl<T: Clone> Arc<[T]> {
+    // Resizing moves the counters, which loom does not expect.
+    fn can_resize(this: &mut Self) -> bool {
+        cfg!(not(feature = "loom")) && Self::get_mut(this).is_some()
+    }
+
+    /// Resizes the allocation of a unique slice to `len` elements. New elements are left
+    /// uninitialized, and cut off ones must have been moved out or dropped.
+    #[cfg(not(feature = "loom"))]
+    unsafe fn resize_unique(this: &mut Self, len: usize) {
+        let old = ArcInner::<[T]>::slice_layout(this.len());
+        let new = ArcInner::<[T]>::slice_layout(len);
+        let mem = alloc::alloc::realloc(this.0.as_ptr().cast(), old, new.size());
+        if mem.is_null() {
+            alloc::alloc::handle_alloc_error(new);
+        }
+        let ptr = ptr::slice_from_raw_parts_mut(mem.cast::<T>(), len) as *mut ArcInner<[T]>;
+        this.0 = NonNull::new_unchecked(ptr);
+    }
+
+    #[cfg(feature = "loom")]
+    unsafe fn resize_unique(_: &mut Self, _: usize) {
+        unreachable!()
+    }
+
+    /// Appends an element, in place if this is the only reference.
+    pub fn push(this: &mut Self, value: T) {
+        let len = this.len();
+        if Self::can_resize(this) {
+            unsafe {
+                Self::resize_unique(this, len + 1);
+                ArcInner::elements(this.0).add(len).write(value);
+            }
+            return;
+        }
+        let mut slice = UniqueArc::with_capacity(len + 1);
+        slice.extend(this.iter().cloned());
+        slice.push(value);
+        *this = UniqueArc::into_arc(slice);
+    }
+
+    /// Appends all `items`. See [`Arc::push`].
+    pub fn extend(this: &mut Self, items: impl IntoIterator<Item = T>) {
+        let len = this.len();
+        Self::splice(this, len.., items);
+    }
+
+    /// Shortens the slice to `len` elements, in place if this is the only reference and
+    /// cloning only the kept ones otherwise.
+    pub fn truncate(this: &mut Self, len: usize) {
+        // Shrinks the allocation once the cut off elements are dropped, even if one panics.
+        struct Shrink<'a, T: Clone>(&'a mut Arc<[T]>, usize);
+
+        impl<T: Clone> Drop for Shrink<'_, T> {
+            fn drop(&mut self) {
+                unsafe { Arc::resize_unique(self.0, self.1) };
+            }
+        }
+
+        let old = this.len();
+        if len >= old {
+            return;
+        }
+        if !Self::can_resize(this) {
+            let mut slice = UniqueArc::with_capacity(len);
+            slice.extend(this[..len].iter().cloned());
+            *this = UniqueArc::into_arc(slice);
+            return;
+        }
+        let elems = ArcInner::elements(this.0);
+        let tail = ptr::slice_from_raw_parts_mut(unsafe { elems.add(len) }, old - len);
+        let _shrink = Shrink(this, len);
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    /// Replaces the elements in `range` with `replace_with`, like [`Vec::splice`]. See
+    /// [`Arc::push`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn splice(
+        this: &mut Self,
+        range: impl core::ops::RangeBounds<usize>,
+        replace_with: impl IntoIterator<Item = T>,
+    ) {
+        use core::ops::Bound;
+
+        let len = this.len();
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).expect("range start overflow"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).expect("range end overflow"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+        assert!(
+            start <= end && end <= len,
+            "splice range {start}..{end} out of bounds for length {len}"
+        );
+        let mut items = replace_with.into_iter().collect::<Vec<_>>();
+        let (removed, added) = (end - start, items.len());
+        let new_len = len - removed + added;
+        if !Self::can_resize(this) {
+            let mut slice = UniqueArc::with_capacity(new_len);
+            slice.extend(this[..start].iter().cloned());
+            slice.extend(items);
+            slice.extend(this[end..].iter().cloned());
+            *this = UniqueArc::into_arc(slice);
+            return;
+        }
+
+        // Trade the removed elements for the new ones, so that `items` drops them once the
+        // slice is whole again.
+        items.reserve(removed.saturating_sub(added));
+        unsafe {
+            let elems = ArcInner::elements(this.0);
+            let moved = items.as_mut_ptr();
+            ptr::swap_nonoverlapping(elems.add(start), moved, removed.min(added));
+            if removed > added {
+                let rest = removed - added;
+                ptr::copy_nonoverlapping(elems.add(start + added), moved.add(added), rest);
+                ptr::copy(elems.add(end), elems.add(start + added), len - end);
+                Self::resize_unique(this, new_len);
+            } else if added > removed {
+                Self::resize_unique(this, new_len);
+                let elems = ArcInner::elements(this.0);
+                ptr::copy(elems.add(end), elems.add(start + added), len - end);
+                ptr::copy_nonoverlapping(moved.add(removed), elems.add(end), added - removed);
+            }
+            items.set_len(removed);
+        }
+    }
+}
+
+impl<T: Copy> Arc<[T]> {
+    /// Creates a slice of `n` copies of `elem`.
+    ///
+    /// Unlike [`Arc::from_elem`], this is a plain fill loop, which compiles down to `memset`
+    /// for byte-sized `T`.
+    pub fn repeat(elem: T, n: usize) -> Self {
+        if n == 0 {
+            return Self::default();
+        }
+        let ptr = ArcInner::<[T]>::allocate_for_slice(n);
+        let elems = ArcInner::elements(ptr);
+        for i in 0..n {
+            unsafe { elems.add(i).write(elem) };
+        }
+        Self(ptr)
+    }
+}
+
+impl<T> From<Vec<T>> for Arc<[T]> {
+    fn from(mut v: Vec<T>) -> Self {
+        if v.is_empty() {
+            return Self::default();
+        }
+        let ptr = ArcInner::allocate_for_slice(v.len());
+        unsafe {
+            ptr::copy_nonoverlapping(v.as_ptr(), ArcInner::elements(ptr), v.len());
+            v.set_len(0);
+        }
+        Self(ptr)
+    }
+}
+
+impl<T: Clone> From<&[T]> for Arc<[T]> {
+    fn from(items: &[T]) -> Self {
+        if items.is_empty() {
+            return Self::default();
+        }
+        let mut slice = UniqueArc::with_capacity(items.len());
+        slice.extend(items.iter().cloned());
+        UniqueArc::into_arc(slice)
+    }
+}
+
+impl<T> FromIterator<T> for Arc<[T]> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Arc<[T]> {
+    fn from(array: [T; N]) -> Self {
+        if N == 0 {
+            return Self::default();
+        }
+        let ptr = ArcInner::allocate_for_slice(N);
+        let array = ManuallyDrop::new(array);
+        unsafe { ptr::copy_nonoverlapping(array.as_ptr(), ArcInner::elements(ptr), N) };
+        Self(ptr)
+    }
+}
+
+impl From<&str> for Arc<str> {
+    fn from(s: &str) -> Self {
+        if s.is_empty() {
+            return Self::default();
+        }
+        let ptr = ArcInner::<[u8]>::allocate_for_slice(s.len());
+        unsafe {
+            ptr::copy_nonoverlapping(s.as_ptr(), ArcInner::elements(ptr), s.len());
+            Self(NonNull::new_unchecked(ptr.as_ptr() as *mut ArcInner<str>))
+        }
+    }
+}
+
+impl From<String> for Arc<str> {
+    fn from(s: String) -> Self {
+        let bytes = Arc::<[u8]>::from(s.into_bytes());
+        let ptr = ManuallyDrop::new(bytes).0.as_ptr() as *mut ArcInner<str>;
+        Self(unsafe { NonNull::new_unchecked(ptr) })
+    }
+}
+
+impl<T> Default for Arc<[T]> {
+    fn default() -> Self {
+        Self(ArcInner::empty())
+    }
+}
+
+impl Default for Arc<str> {
+    fn default() -> Self {
+        let ptr = ArcInner::<[u8]>::empty().as_ptr() as *mut ArcInner<str>;
+        Self(unsafe { NonNull::new_unchecked(ptr) })
+    }
+}
+
+impl<T: Clone> IntoIterator for Arc<[T]> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let this = ManuallyDrop::new(self);
+        let owned = unsafe { this.0.as_ref().try_close() };
+        if owned {
+            unsafe { this.0.as_ref() }.record_drop();
+        }
+        IntoIter {
+            ptr: this.0,
+            owned,
+            start: 0,
+            end: this.len(),
+        }
+    }
+}
+
+/// An iterator that moves elements out of an `Arc<[T]>` if it was the only strong reference,
+/// and clones them otherwise.
+pub struct IntoIter<T> {
+    ptr: NonNull<ArcInner<[T]>>,
+    owned: bool,
+    start: usize,
+    end: usize,
+}
+
+unsafe impl<T: Send + Sync> Send for IntoIter<T> {}
+unsafe impl<T: Send + Sync> Sync for IntoIter<T> {}
+
+impl<T> IntoIter<T> {
+    fn get(&self, idx: usize) -> T
+    where
+        T: Clone,
+    {
+        let elem = unsafe { ArcInner::elements(self.ptr).add(idx) };
+        if self.owned {
+            unsafe { elem.read() }
+        } else {
+            unsafe { (*elem).clone() }
+        }
+    }
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.start += 1;
+        Some(self.get(self.start - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.get(self.end))
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for IntoIter<T> {}
+
+impl<T: Clone> FusedIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        if !self.owned {
+            drop(Arc(self.ptr));
+            return;
+        }
+
+        struct ReleaseOnDrop<T>(NonNull<ArcInner<[T]>>);
+        impl<T> Drop for ReleaseOnDrop<T> {
+            fn drop(&mut self) {
+                unsafe { ArcInner::release_closed(self.0) }
+            }
+        }
+
+        let _guard = ReleaseOnDrop(self.ptr);
+        unsafe {
+            let rest = ArcInner::elements(self.ptr).add(self.start);
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(rest, self.end - self.start));
+        }
+    }
+}
+
+/// An iterator of [`ProjectedArc`]s to the elements of an `Arc<[T]>`, created by
+/// [`Arc::element_arcs`].
+pub struct ElementArcs<T> {
+    arc: Arc<[T]>,
+    range: Range<usize>,
+}
+
+impl<T> Iterator for ElementArcs<T> {
+    type Item = ProjectedArc<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.range.next()?;
+        Some(ProjectedArc::from_element(self.arc.clone(), idx))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for ElementArcs<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let idx = self.range.next_back()?;
+        Some(ProjectedArc::from_element(self.arc.clone(), idx))
+    }
+}
+
+impl<T> ExactSizeIterator for ElementArcs<T> {}
+
+impl<T> FusedIterator for ElementArcs<T> {}
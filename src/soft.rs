@@ -0,0 +1,107 @@
+//! Soft references: strong until memory pressure asks for them back via [`trim`].
+use std::sync::{Arc as StdArc, Mutex, MutexGuard, PoisonError, Weak as StdWeak};
+
+use crate::{Arc, Weak};
+
+// Registration order, so trimming releases the oldest entries first.
+static REGISTRY: Mutex<Vec<StdWeak<dyn Trim>>> = Mutex::new(Vec::new());
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+trait Trim: Send + Sync {
+    /// Drops the strong reference, returning the size of the allocation it kept alive.
+    fn trim(&self) -> Option<usize>;
+}
+
+struct Entry<T: ?Sized> {
+    strong: Mutex<Option<Arc<T>>>,
+    weak: Weak<T>,
+}
+
+impl<T: ?Sized + Send + Sync> Trim for Entry<T> {
+    fn trim(&self) -> Option<usize> {
+        let arc = lock(&self.strong).take()?;
+        Some(Arc::heap_size(&arc))
+    }
+}
+
+/// A reference that behaves like a strong one until [`trim`] is called, after which it only
+/// keeps a weak reference.
+pub struct SoftArc<T: ?Sized>(StdArc<Entry<T>>);
+
+impl<T: ?Sized + Send + Sync + 'static> SoftArc<T> {
+    pub fn new(arc: Arc<T>) -> Self {
+        let entry = StdArc::new(Entry {
+            weak: Arc::downgrade(&arc),
+            strong: Mutex::new(Some(arc)),
+        });
+        let mut registry = lock(&REGISTRY);
+        if registry.len() == registry.capacity() {
+            registry.retain(|entry| entry.strong_count() != 0);
+        }
+        registry.push(StdArc::downgrade(&entry) as StdWeak<dyn Trim>);
+        Self(entry)
+    }
+}
+
+impl<T: ?Sized + Send + Sync + 'static> From<Arc<T>> for SoftArc<T> {
+    fn from(arc: Arc<T>) -> Self {
+        Self::new(arc)
+    }
+}
+
+impl<T: ?Sized> SoftArc<T> {
+    /// Returns a strong reference, or `None` if this was trimmed and the value is gone.
+    pub fn get(&self) -> Option<Arc<T>> {
+        if let Some(arc) = &*lock(&self.0.strong) {
+            return Some(arc.clone());
+        }
+        self.0.weak.upgrade()
+    }
+
+    pub fn is_trimmed(&self) -> bool {
+        lock(&self.0.strong).is_none()
+    }
+}
+
+/// Clones share the soft reference, so they are trimmed together.
+impl<T: ?Sized> Clone for SoftArc<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> std::fmt::Debug for SoftArc<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SoftArc")
+            .field("trimmed", &self.is_trimmed())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Degrades soft references to weak ones, oldest first, until at least `bytes` worth of
+/// allocations are no longer kept alive by them, or all of them for `None`.
+///
+/// Returns the number of bytes released this way. Allocations still referenced elsewhere are
+/// counted but not actually freed.
+pub fn trim(bytes: Option<usize>) -> usize {
+    // Values are dropped outside the registry lock, since their destructors may register more.
+    let entries = {
+        let mut registry = lock(&REGISTRY);
+        registry.retain(|entry| entry.strong_count() != 0);
+        registry
+            .iter()
+            .filter_map(StdWeak::upgrade)
+            .collect::<Vec<_>>()
+    };
+    let mut released = 0;
+    for entry in entries {
+        if bytes.is_some_and(|bytes| released >= bytes) {
+            break;
+        }
+        released += entry.trim().unwrap_or(0);
+    }
+    released
+}
@@ -0,0 +1,362 @@
+use core::fmt;
+use core::ptr::NonNull;
+
+use crate::{
+    abort, acquire_fence, ArcInner, AtomicCount, Count, DebugState, Ordering, MAX_REFCOUNT,
+};
+
+const WEAK_EXIST: Count = 1;
+const CLOSED: Count = 2;
+const SINGLE_STRONG: Count = 4;
+#[cfg(not(feature = "lock"))]
+const SINGLE_WEAK: Count = 1;
+
+// With `lock`, the low bits of the weak counter hold an integrated mutex: whether it is locked,
+// and whether any thread may be parked waiting for it. Only strong references can lock, so both
+// bits are clear whenever the weak count can reach zero.
+#[cfg(feature = "lock")]
+const LOCKED: Count = 1;
+#[cfg(feature = "lock")]
+const PARKED: Count = 2;
+#[cfg(feature = "lock")]
+const SINGLE_WEAK: Count = 4;
+#[cfg(not(feature = "lock"))]
+const LOCK_BITS: Count = 0;
+#[cfg(feature = "lock")]
+const LOCK_BITS: Count = LOCKED | PARKED;
+
+// Set in the weak counter by `is_unique` while it checks the strong count. Aborting on overflow
+// keeps the count itself below this bit.
+const CHECKING: Count = !MAX_REFCOUNT;
+
+const _: () = {
+    assert!(WEAK_EXIST & CLOSED == 0 && WEAK_EXIST + CLOSED < SINGLE_STRONG);
+    // Leave room for at least a few thousand strong references on 16-bit targets.
+    assert!(MAX_REFCOUNT / SINGLE_STRONG >= 4096);
+};
+
+pub(crate) struct Counters {
+    strong: AtomicCount,
+    weak: AtomicCount,
+    #[cfg(feature = "diagnostics")]
+    pub(crate) diag: crate::diag::DiagSlot,
+}
+
+impl Counters {
+    pub(crate) fn new() -> Self {
+        Self {
+            strong: SINGLE_STRONG.into(),
+            weak: 0.into(),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    /// Counters of a static allocation that is never released. The strong count starts so high
+    /// that it cannot drop to zero, yet leaves as much room for clones as for releases.
+    #[cfg(not(feature = "loom"))]
+    pub(crate) const fn new_static() -> Self {
+        Self {
+            strong: AtomicCount::new(MAX_REFCOUNT / 2 / SINGLE_STRONG * SINGLE_STRONG),
+            weak: AtomicCount::new(0),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    /// Counters of an allocation whose value is still being constructed: closed, with one weak
+    /// reference for the constructor to hand out besides the implicit one.
+    pub(crate) fn new_cyclic() -> Self {
+        Self {
+            strong: CLOSED.into(),
+            weak: (SINGLE_WEAK * 2).into(),
+            #[cfg(feature = "diagnostics")]
+            diag: crate::diag::DiagSlot::new(),
+        }
+    }
+
+    pub(crate) fn debug_fields(&self, f: &mut fmt::DebugStruct<'_, '_>) {
+        f.field("strong", &self.strong.load(Ordering::Relaxed))
+            .field("weak", &self.weak.load(Ordering::Relaxed));
+    }
+}
+
+impl<T: ?Sized> ArcInner<T> {
+    pub(crate) fn acquire_strong_from_strong(&self) {
+        let old = self
+            .counters
+            .strong
+            .fetch_add(SINGLE_STRONG, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            abort();
+        }
+    }
+
+    pub(crate) fn acquire_strong_from_strong_unchecked(&self) {
+        self.counters
+            .strong
+            .fetch_add(SINGLE_STRONG, Ordering::Relaxed);
+    }
+
+    pub(crate) fn acquire_strong_from_weak(&self) -> bool {
+        let old = self
+            .counters
+            .strong
+            .fetch_add(SINGLE_STRONG, Ordering::Acquire);
+        // Failed upgrades keep bumping a closed counter. Let it wrap instead of aborting, since
+        // adding multiples of `SINGLE_STRONG` never clears `CLOSED`.
+        if old & CLOSED != 0 {
+            return false;
+        }
+        if old > MAX_REFCOUNT {
+            abort();
+        }
+        // Resurrecting from `WEAK_EXIST` alone races with the strong release that is about to
+        // try closing, which still needs the implicit weak reference to keep the allocation
+        // alive. So take another one rather than sharing it.
+        if old < SINGLE_STRONG {
+            debug_assert_eq!(old, WEAK_EXIST);
+            let old_weak = self.counters.weak.fetch_add(SINGLE_WEAK, Ordering::Relaxed);
+            if old_weak > MAX_REFCOUNT {
+                abort();
+            }
+        }
+        true
+    }
+
+    /// Opens an allocation created with `Counters::new_cyclic` with a single strong reference.
+    pub(crate) fn finish_cyclic(&self) {
+        // Failed upgrades only ever add garbage to the closed count, so it can be overwritten.
+        self.counters
+            .strong
+            .swap(SINGLE_STRONG + WEAK_EXIST, Ordering::Release);
+    }
+
+    pub(crate) unsafe fn release_strong(this: NonNull<Self>) {
+        Self::release_strong_many(this, 1);
+    }
+
+    /// Releases `n` strong references at once.
+    pub(crate) unsafe fn release_strong_many(this: NonNull<Self>, n: Count) {
+        Self::release_strong_many_with(this, n, |inner| inner.drop_inner());
+    }
+
+    /// Like `release_strong_many`, but calls `last` instead of dropping the value.
+    pub(crate) unsafe fn release_strong_many_with(
+        mut this: NonNull<Self>,
+        n: Count,
+        last: impl FnOnce(&mut Self),
+    ) {
+        let counters = &this.as_ref().counters;
+        let old = counters
+            .strong
+            .fetch_sub(SINGLE_STRONG * n, Ordering::Release);
+        if old > SINGLE_STRONG * n + WEAK_EXIST {
+            return;
+        }
+        if old & WEAK_EXIST == 0 {
+            acquire_fence(&counters.strong);
+            last(this.as_mut());
+            Self::dealloc(this);
+            return;
+        }
+        if counters
+            .strong
+            .compare_exchange(WEAK_EXIST, CLOSED, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+        {
+            last(this.as_mut());
+        }
+        Self::release_weak(this);
+    }
+
+    /// Converts a strong reference into a weak one.
+    pub(crate) unsafe fn release_strong_into_weak(mut this: NonNull<Self>) {
+        if !this.as_ref().try_close() {
+            this.as_ref().acquire_weak_from_strong();
+            Self::release_strong(this);
+            return;
+        }
+        // The implicit weak reference becomes the returned one. Without one, nobody else can
+        // reach the weak counter anymore.
+        let weak = &this.as_ref().counters.weak;
+        if weak.load(Ordering::Relaxed) == 0 {
+            weak.store(SINGLE_WEAK, Ordering::Relaxed);
+        }
+        #[cfg(feature = "diagnostics")]
+        this.as_ref().counters.diag.record_drop();
+        this.as_mut().drop_inner();
+    }
+
+    pub(crate) fn strong_count(&self, order: core::sync::atomic::Ordering) -> usize {
+        let strong = self.counters.strong.load(order);
+        if strong & CLOSED != 0 {
+            return 0;
+        }
+        crate::count_to_usize(strong / SINGLE_STRONG)
+    }
+
+    /// The number of weak references, excluding the implicit one, or zero once the value is
+    /// gone.
+    pub(crate) fn weak_count(&self, order: core::sync::atomic::Ordering) -> usize {
+        let weak = self.counters.weak.load(order) & !CHECKING;
+        if self.counters.strong.load(order) & CLOSED != 0 {
+            return 0;
+        }
+        crate::count_to_usize((weak / SINGLE_WEAK).saturating_sub(1))
+    }
+
+    /// Whether weak references besides the implicit one exist, once closed.
+    #[cfg(feature = "diagnostics")]
+    pub(crate) fn weak_remains(&self) -> bool {
+        self.counters.weak.load(Ordering::Relaxed) & !(CHECKING | LOCK_BITS) > SINGLE_WEAK
+    }
+
+    pub(crate) fn debug_state(&self) -> DebugState {
+        let strong = self.counters.strong.load(Ordering::Relaxed);
+        DebugState {
+            strong: crate::count_to_usize(strong / SINGLE_STRONG),
+            weak: crate::count_to_usize(
+                (self.counters.weak.load(Ordering::Relaxed) & !CHECKING) / SINGLE_WEAK,
+            ),
+            weak_exist: strong & WEAK_EXIST != 0,
+            closed: strong & CLOSED != 0,
+        }
+    }
+
+    /// Whether this is the only reference, strong or weak.
+    pub(crate) fn is_unique(&self) -> bool {
+        let counters = &self.counters;
+        match counters.strong.load(Ordering::Acquire) {
+            SINGLE_STRONG => true,
+            // Only the implicit weak reference may remain, but `WEAK_EXIST` stays set.
+            strong if strong == SINGLE_STRONG + WEAK_EXIST => {
+                // Hold off downgrades while checking the strong count again, otherwise another
+                // strong reference could downgrade and drop in between.
+                if counters
+                    .weak
+                    .compare_exchange(
+                        SINGLE_WEAK,
+                        SINGLE_WEAK | CHECKING,
+                        Ordering::Acquire,
+                        Ordering::Relaxed,
+                    )
+                    .is_err()
+                {
+                    return false;
+                }
+                let unique = counters.strong.load(Ordering::Acquire) == strong;
+                // Lock bits may have been set meanwhile, so only clear ours.
+                counters.weak.fetch_and(!CHECKING, Ordering::Release);
+                unique
+            }
+            _ => false,
+        }
+    }
+
+    /// Gives up the last strong reference without dropping the value, so the caller can take it
+    /// over. Returns `false` if other strong references exist.
+    ///
+    /// After success, the caller must eventually call `release_closed`.
+    pub(crate) unsafe fn try_close(&self) -> bool {
+        let strong = &self.counters.strong;
+        match strong.compare_exchange(SINGLE_STRONG, CLOSED, Ordering::Acquire, Ordering::Relaxed) {
+            Ok(_) => true,
+            Err(cur) => {
+                cur == SINGLE_STRONG + WEAK_EXIST
+                    && strong
+                        .compare_exchange(cur, CLOSED, Ordering::Acquire, Ordering::Relaxed)
+                        .is_ok()
+            }
+        }
+    }
+
+    pub(crate) unsafe fn release_closed(this: NonNull<Self>) {
+        // Without `WEAK_EXIST` nobody could have created a weak reference before closing.
+        if this.as_ref().counters.weak.load(Ordering::Relaxed) == 0 {
+            Self::dealloc(this);
+        } else {
+            Self::release_weak(this);
+        }
+    }
+
+    pub(crate) fn acquire_weak_from_strong(&self) {
+        let counters = &self.counters;
+        let mut cur = counters.weak.load(Ordering::Relaxed);
+        // The lock bits may be set even before the first weak reference.
+        while cur & !LOCK_BITS == 0 {
+            match counters.weak.compare_exchange(
+                cur,
+                cur + SINGLE_WEAK * 2,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    counters.strong.fetch_add(WEAK_EXIST, Ordering::Relaxed);
+                    return;
+                }
+                Err(actual) => cur = actual,
+            }
+        }
+        loop {
+            let old = counters.weak.fetch_add(SINGLE_WEAK, Ordering::Relaxed);
+            if old & CHECKING == 0 {
+                if old > MAX_REFCOUNT {
+                    abort();
+                }
+                return;
+            }
+            // `is_unique` must not see this reference come and go, so back off until it is done.
+            counters.weak.fetch_sub(SINGLE_WEAK, Ordering::Relaxed);
+            while counters.weak.load(Ordering::Relaxed) & CHECKING != 0 {
+                crate::spin_loop();
+            }
+        }
+    }
+
+    pub(crate) fn acquire_weak_from_weak(&self) {
+        let old = self.counters.weak.fetch_add(SINGLE_WEAK, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            abort();
+        }
+    }
+
+    pub(crate) unsafe fn release_weak(this: NonNull<Self>) {
+        let counters = &this.as_ref().counters;
+        // Released so that `is_unique` sees the strong count this reference may have upgraded.
+        if counters.weak.fetch_sub(SINGLE_WEAK, Ordering::Release) == SINGLE_WEAK {
+            acquire_fence(&counters.weak);
+            Self::dealloc(this);
+        }
+    }
+}
+
+#[cfg(feature = "lock")]
+impl Counters {
+    pub(crate) fn try_lock(&self) -> bool {
+        self.weak.fetch_or(LOCKED, Ordering::Acquire) & LOCKED == 0
+    }
+
+    /// Records that the caller is about to park until unlocked. Returns `false` if the lock
+    /// was released in the meantime.
+    pub(crate) fn mark_parked(&self) -> bool {
+        let mut cur = self.weak.load(Ordering::Relaxed);
+        while cur & LOCKED != 0 {
+            match self.weak.compare_exchange_weak(
+                cur,
+                cur | PARKED,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => cur = actual,
+            }
+        }
+        false
+    }
+
+    /// Releases the lock. Returns whether parked threads need to be woken.
+    pub(crate) fn unlock(&self) -> bool {
+        self.weak.fetch_and(!LOCK_BITS, Ordering::Release) & PARKED != 0
+    }
+}
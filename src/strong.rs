@@ -0,0 +1,130 @@
+use core::alloc::Layout;
+use core::mem::ManuallyDrop;
+use core::ptr::{self, NonNull};
+use core::{fmt, ops};
+
+use crate::{abort, acquire_fence, alloc, dealloc, Arc, AtomicCount, Ordering, MAX_REFCOUNT};
+
+#[repr(C)]
+struct StrongInner<T> {
+    count: AtomicCount,
+    value: T,
+}
+
+// Loom's atomics carry extra state.
+#[cfg(not(feature = "loom"))]
+const _: () =
+    assert!(core::mem::size_of::<StrongInner<()>>() == core::mem::size_of::<crate::Count>());
+
+/// An [`Arc`] without weak references, and thus with a single counter in the header and no
+/// state machine on drop.
+///
+/// Convert it with [`StrongArc::into_arc`] once weak references are needed.
+pub struct StrongArc<T>(NonNull<StrongInner<T>>);
+
+unsafe impl<T: Send + Sync> Send for StrongArc<T> {}
+unsafe impl<T: Send + Sync> Sync for StrongArc<T> {}
+
+impl<T> StrongArc<T> {
+    pub fn new(value: T) -> Self {
+        let layout = Layout::new::<StrongInner<T>>();
+        let ptr = unsafe { alloc(layout).cast::<StrongInner<T>>() };
+        let Some(ptr) = NonNull::new(ptr) else {
+            ::alloc::alloc::handle_alloc_error(layout);
+        };
+        unsafe {
+            ptr.as_ptr().write(StrongInner {
+                count: AtomicCount::new(1),
+                value,
+            })
+        };
+        Self(ptr)
+    }
+
+    fn inner(&self) -> &StrongInner<T> {
+        unsafe { self.0.as_ref() }
+    }
+
+    /// The number of strong references.
+    pub fn strong_count(this: &Self) -> usize {
+        crate::count_to_usize(this.inner().count.load(Ordering::Acquire))
+    }
+
+    /// Moves the value out if this is the only reference.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        let count = &this.inner().count;
+        if count
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        unsafe {
+            let value = ptr::addr_of!((*this.0.as_ptr()).value).read();
+            dealloc(this.0.as_ptr().cast(), Layout::new::<StrongInner<T>>());
+            Ok(value)
+        }
+    }
+
+    /// Moves the value into an [`Arc`] if this is the only reference, or gives back this one.
+    pub fn try_into_arc(this: Self) -> Result<Arc<T>, Self> {
+        Self::try_unwrap(this).map(Arc::new)
+    }
+
+    /// Converts into an [`Arc`], moving the value if this is the only reference and cloning it
+    /// otherwise.
+    pub fn into_arc(this: Self) -> Arc<T>
+    where
+        T: Clone,
+    {
+        Self::try_into_arc(this).unwrap_or_else(|this| Arc::new(T::clone(&this)))
+    }
+}
+
+impl<T> Clone for StrongArc<T> {
+    fn clone(&self) -> Self {
+        let old = self.inner().count.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            abort();
+        }
+        Self(self.0)
+    }
+}
+
+impl<T> Drop for StrongArc<T> {
+    fn drop(&mut self) {
+        let count = &self.inner().count;
+        if count.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        acquire_fence(count);
+        unsafe {
+            ptr::drop_in_place(ptr::addr_of_mut!((*self.0.as_ptr()).value));
+            dealloc(self.0.as_ptr().cast(), Layout::new::<StrongInner<T>>());
+        }
+    }
+}
+
+impl<T> ops::Deref for StrongArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T> From<T> for StrongArc<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for StrongArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StrongArc")
+            .field("strong", &self.inner().count.load(Ordering::Relaxed))
+            .field("inner", &self.inner().value)
+            .finish()
+    }
+}
@@ -0,0 +1,130 @@
+//! `Arc<Mutex<T>>` and `Arc<RwLock<T>>` as single types.
+//!
+//! Poisoning is ignored: a lock whose holder panicked is handed out as if nothing happened.
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{Arc, Weak};
+
+pub struct ArcMutex<T: ?Sized>(Arc<Mutex<T>>);
+
+pub struct WeakMutex<T: ?Sized>(Weak<Mutex<T>>);
+
+impl<T> ArcMutex<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(Mutex::new(value)))
+    }
+
+    /// Returns the value if this is the last strong handle.
+    pub fn try_unwrap(self) -> Result<T, Self> {
+        match Arc::try_unwrap(self.0) {
+            Ok(mutex) => Ok(mutex.into_inner().unwrap_or_else(PoisonError::into_inner)),
+            Err(arc) => Err(Self(arc)),
+        }
+    }
+}
+
+impl<T: ?Sized> ArcMutex<T> {
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        match self.0.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(std::sync::TryLockError::Poisoned(err)) => Some(err.into_inner()),
+            Err(std::sync::TryLockError::WouldBlock) => None,
+        }
+    }
+
+    pub fn downgrade(&self) -> WeakMutex<T> {
+        WeakMutex(Arc::downgrade(&self.0))
+    }
+}
+
+impl<T: ?Sized> WeakMutex<T> {
+    pub fn upgrade(&self) -> Option<ArcMutex<T>> {
+        self.0.upgrade().map(ArcMutex)
+    }
+}
+
+pub struct ArcRwLock<T: ?Sized>(Arc<RwLock<T>>);
+
+pub struct WeakRwLock<T: ?Sized>(Weak<RwLock<T>>);
+
+impl<T> ArcRwLock<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(RwLock::new(value)))
+    }
+
+    /// Returns the value if this is the last strong handle.
+    pub fn try_unwrap(self) -> Result<T, Self> {
+        match Arc::try_unwrap(self.0) {
+            Ok(lock) => Ok(lock.into_inner().unwrap_or_else(PoisonError::into_inner)),
+            Err(arc) => Err(Self(arc)),
+        }
+    }
+}
+
+impl<T: ?Sized> ArcRwLock<T> {
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn downgrade(&self) -> WeakRwLock<T> {
+        WeakRwLock(Arc::downgrade(&self.0))
+    }
+}
+
+impl<T: ?Sized> WeakRwLock<T> {
+    pub fn upgrade(&self) -> Option<ArcRwLock<T>> {
+        self.0.upgrade().map(ArcRwLock)
+    }
+}
+
+macro_rules! impl_common {
+    ($arc:ident, $weak:ident) => {
+        impl<T: ?Sized> Clone for $arc<T> {
+            fn clone(&self) -> Self {
+                Self(self.0.clone())
+            }
+        }
+
+        impl<T: ?Sized> Clone for $weak<T> {
+            fn clone(&self) -> Self {
+                Self(self.0.clone())
+            }
+        }
+
+        impl<T: Default> Default for $arc<T> {
+            fn default() -> Self {
+                Self::new(T::default())
+            }
+        }
+
+        impl<T> From<T> for $arc<T> {
+            fn from(value: T) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl<T: ?Sized + fmt::Debug> fmt::Debug for $arc<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_tuple(stringify!($arc)).field(&&*self.0).finish()
+            }
+        }
+
+        impl<T: ?Sized> fmt::Debug for $weak<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(stringify!($weak))
+            }
+        }
+    };
+}
+
+impl_common!(ArcMutex, WeakMutex);
+impl_common!(ArcRwLock, WeakRwLock);
@@ -0,0 +1,141 @@
+use core::future::Future;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::pin::Pin;
+use core::ptr::NonNull;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use core::{fmt, ops};
+
+use crate::{Arc, ArcInner, Weak};
+
+/// The implementation of waking a task on an executor, usable to build a [`Waker`] out of an
+/// [`Arc`] of this crate.
+pub trait Wake {
+    fn wake(this: Arc<Self>);
+
+    fn wake_by_ref(this: &Arc<Self>) {
+        Self::wake(this.clone());
+    }
+}
+
+impl<W: Wake + Send + Sync + 'static> Arc<W> {
+    pub fn into_waker(this: Self) -> Waker {
+        let data = ManuallyDrop::new(this).0.as_ptr() as *const ();
+        unsafe { Waker::from_raw(RawWaker::new(data, vtable::<W>())) }
+    }
+}
+
+impl<W: Wake + Send + Sync + 'static> From<Arc<W>> for Waker {
+    fn from(arc: Arc<W>) -> Self {
+        Arc::into_waker(arc)
+    }
+}
+
+fn vtable<W: Wake + Send + Sync + 'static>() -> &'static RawWakerVTable {
+    &RawWakerVTable::new(
+        clone_waker::<W>,
+        wake::<W>,
+        wake_by_ref::<W>,
+        drop_waker::<W>,
+    )
+}
+
+unsafe fn borrow<W>(data: *const ()) -> ManuallyDrop<Arc<W>> {
+    ManuallyDrop::new(Arc(NonNull::new_unchecked(data as *mut ArcInner<W>)))
+}
+
+unsafe fn clone_waker<W: Wake + Send + Sync + 'static>(data: *const ()) -> RawWaker {
+    let _ = ManuallyDrop::new((*borrow::<W>(data)).clone());
+    RawWaker::new(data, vtable::<W>())
+}
+
+unsafe fn wake<W: Wake + Send + Sync + 'static>(data: *const ()) {
+    W::wake(ManuallyDrop::into_inner(borrow(data)));
+}
+
+unsafe fn wake_by_ref<W: Wake + Send + Sync + 'static>(data: *const ()) {
+    W::wake_by_ref(&borrow(data));
+}
+
+unsafe fn drop_waker<W: Wake + Send + Sync + 'static>(data: *const ()) {
+    drop(ManuallyDrop::into_inner(borrow::<W>(data)));
+}
+
+/// A [`Waker`] borrowing an `Arc`, which avoids touching the reference count unless the waker
+/// is cloned.
+#[derive(Debug)]
+pub struct WakerRef<'a> {
+    waker: ManuallyDrop<Waker>,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl ops::Deref for WakerRef<'_> {
+    type Target = Waker;
+
+    fn deref(&self) -> &Self::Target {
+        &self.waker
+    }
+}
+
+pub fn waker_ref<W: Wake + Send + Sync + 'static>(arc: &Arc<W>) -> WakerRef<'_> {
+    let data = arc.0.as_ptr() as *const ();
+    WakerRef {
+        waker: ManuallyDrop::new(unsafe { Waker::from_raw(RawWaker::new(data, vtable::<W>())) }),
+        _marker: PhantomData,
+    }
+}
+
+/// Wraps `future` so that it only runs while `owner` is alive. Once the owner is gone, the next
+/// poll drops the future and resolves to `None`.
+pub fn guarded<T: ?Sized, F: Future>(owner: Weak<T>, future: F) -> Guarded<T, F> {
+    Guarded {
+        owner,
+        future: Some(future),
+    }
+}
+
+/// The future returned by [`guarded`].
+#[must_use = "futures do nothing unless polled"]
+pub struct Guarded<T: ?Sized, F> {
+    owner: Weak<T>,
+    future: Option<F>,
+}
+
+impl<T: ?Sized, F> Guarded<T, F> {
+    /// Whether the owner is gone, in which case the next poll resolves to `None`.
+    pub fn is_orphaned(&self) -> bool {
+        !self.owner.is_alive()
+    }
+}
+
+impl<T: ?Sized, F> fmt::Debug for Guarded<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Guarded")
+            .field("orphaned", &self.is_orphaned())
+            .field("done", &self.future.is_none())
+            .finish()
+    }
+}
+
+// The owner is never pinned.
+impl<T: ?Sized, F: Unpin> Unpin for Guarded<T, F> {}
+
+impl<T: ?Sized, F: Future> Future for Guarded<T, F> {
+    type Output = Option<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is structurally pinned and only ever dropped in place.
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let Some(inner) = future.as_mut().as_pin_mut() else {
+            panic!("`Guarded` polled after completion");
+        };
+        if !this.owner.is_alive() {
+            future.set(None);
+            return Poll::Ready(None);
+        }
+        let output = core::task::ready!(inner.poll(cx));
+        future.set(None);
+        Poll::Ready(Some(output))
+    }
+}
@@ -0,0 +1,110 @@
+//! The harness behind this crate's own tests, for crates built on top of it to model-check
+//! their code the same way.
+//!
+//! With the `loom` feature, everything here comes from `loom`, and [`model`] explores all
+//! interleavings. Without it, it comes from `std`, and [`model`] simply runs the closure many
+//! times.
+//!
+//! [`assert_no_alloc_or_block`] checks that code is fit for real-time threads.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::Cell;
+
+#[cfg(feature = "loom")]
+pub use loom::{model, sync, thread};
+
+#[cfg(not(feature = "loom"))]
+pub use std::{sync, thread};
+
+/// Runs `f` a thousand times, hoping to hit interesting interleavings.
+#[cfg(not(feature = "loom"))]
+pub fn model<F: Fn() + Sync + Send + 'static>(f: F) {
+    const ROUNDS: usize = 1_000;
+    for _ in 0..ROUNDS {
+        f();
+    }
+}
+
+/// A value that knows whether all of its clones have been dropped, for checking that a
+/// container drops its contents exactly when expected.
+#[derive(Debug, Default, Clone)]
+pub struct DropMonitor(sync::Arc<()>);
+
+impl DropMonitor {
+    /// Whether all other clones have been dropped.
+    pub fn is_unique(&self) -> bool {
+        sync::Arc::strong_count(&self.0) == 1
+    }
+}
+
+thread_local! {
+    static CHECKING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+    static BLOCKS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record(counter: &'static std::thread::LocalKey<Cell<usize>>) {
+    // Also called from the allocator, possibly while thread locals are being torn down.
+    if CHECKING.try_with(Cell::get).unwrap_or(false) {
+        let _ = counter.try_with(|n| n.set(n.get() + 1));
+    }
+}
+
+/// Reports that this crate is about to block the current thread.
+pub(crate) fn record_block() {
+    record(&BLOCKS);
+}
+
+/// A global allocator that reports to [`assert_no_alloc_or_block`], and otherwise forwards to
+/// `A`. Install it with `#[global_allocator]` in the test binary to have allocations checked.
+pub struct CheckedAlloc<A>(A);
+
+impl<A> CheckedAlloc<A> {
+    pub const fn new(alloc: A) -> Self {
+        Self(alloc)
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CheckedAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(&ALLOCS);
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(&ALLOCS);
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record(&ALLOCS);
+        self.0.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(&ALLOCS);
+        self.0.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Runs `f`, then panics if it allocated, deallocated or blocked on the current thread.
+///
+/// Allocations are only seen with [`CheckedAlloc`] installed. Blocking is seen where this crate
+/// parks a thread, such as a contended `lock::Locked` or [`WaitGroup::wait`], but not inside
+/// other code.
+///
+/// [`WaitGroup::wait`]: crate::wait_group::WaitGroup::wait
+#[track_caller]
+pub fn assert_no_alloc_or_block<R>(f: impl FnOnce() -> R) -> R {
+    ALLOCS.with(|n| n.set(0));
+    BLOCKS.with(|n| n.set(0));
+    let was_checking = CHECKING.with(|c| c.replace(true));
+    let ret = f();
+    CHECKING.with(|c| c.set(was_checking));
+    let (allocs, blocks) = (ALLOCS.with(Cell::get), BLOCKS.with(Cell::get));
+    assert!(
+        allocs == 0 && blocks == 0,
+        "unexpected (de)allocations: {allocs}, blocking: {blocks}"
+    );
+    ret
+}
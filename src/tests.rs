@@ -1,68 +1,1941 @@
-use crate::Arc;
+use crate::test_util::{model, thread, DropMonitor};
+use crate::{Arc, Weak};
 
-#[cfg(feature = "loom")]
-use loom::{model, sync, thread};
+fn new_monitored_arc() -> (DropMonitor, Arc<DropMonitor>) {
+    let monitor = DropMonitor::default();
+    let arc = Arc::new(monitor.clone());
+    (monitor, arc)
+}
+
+/// Counts allocations that the current thread made and has yet to free, on top of the checks
+/// of `test_util::assert_no_alloc_or_block`.
+#[cfg(not(feature = "loom"))]
+mod live_alloc {
+    use crate::test_util::CheckedAlloc;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    thread_local! {
+        static LIVE: Cell<isize> = const { Cell::new(0) };
+    }
+
+    struct CountingAlloc(CheckedAlloc<System>);
+
+    fn add(n: isize) {
+        let _ = LIVE.try_with(|live| live.set(live.get() + n));
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            add(1);
+            self.0.alloc(layout)
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            add(1);
+            self.0.alloc_zeroed(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            add(-1);
+            self.0.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            self.0.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc(CheckedAlloc::new(System));
+
+    pub fn live_allocations() -> isize {
+        LIVE.with(Cell::get)
+    }
+}
+
+#[test]
+fn trivial_drop() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        assert!(!monitor.is_unique());
+        drop(v1);
+        assert!(monitor.is_unique());
+    });
+}
+
+#[test]
+fn trivial_clone() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let v2 = v1.clone();
+        let v3 = v2.clone();
+        drop(v1);
+        drop(v2);
+        assert!(!monitor.is_unique());
+        drop(v3);
+        assert!(monitor.is_unique());
+    });
+}
+
+#[test]
+fn trivial_clone_unchecked() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let v2 = unsafe { Arc::clone_unchecked(&v1) };
+        drop(v1);
+        assert!(!monitor.is_unique());
+        drop(v2);
+        assert!(monitor.is_unique());
+    });
+}
+
+#[test]
+fn allocation_layout() {
+    use core::mem::{align_of, size_of};
+    model(|| {
+        let v = Arc::new([0u64; 3]);
+        let layout = Arc::allocation_layout(&v);
+        assert_eq!(layout.size(), size_of::<crate::ArcInner<[u64; 3]>>());
+        assert_eq!(layout.align(), align_of::<crate::ArcInner<[u64; 3]>>());
+        assert!(Arc::heap_size(&v) >= 3 * 8 + 2);
+
+        assert!(Arc::heap_size(&Arc::<[u8]>::from(&[1][..])) > 0);
+        #[cfg(not(feature = "loom"))]
+        {
+            assert_eq!(Arc::heap_size(&Arc::<[u8]>::default()), 0);
+            assert_eq!(Arc::heap_size(&Arc::<str>::default()), 0);
+        }
+    });
+}
+
+#[test]
+fn eq() {
+    model(|| {
+        let v1 = Arc::new(42);
+        assert_eq!(v1, Arc::new(42));
+        assert_eq!(v1, 42);
+        assert_ne!(v1, 0);
+
+        let s = Arc::<str>::from("foo");
+        assert_eq!(s, *"foo");
+        assert_eq!(s, "foo");
+        assert_eq!(*"foo", s);
+        assert_eq!("foo", s);
+        assert_ne!(s, "bar");
+
+        let v2 = Arc::<[i32]>::from([1, 2, 3]);
+        assert_eq!(v2, [1, 2, 3]);
+        assert_eq!([1, 2, 3], v2);
+        assert_eq!(v2, [1, 2, 3][..]);
+        assert_eq!(v2, &[1, 2, 3][..]);
+        assert_eq!([1, 2, 3][..], v2);
+        assert_eq!(&[1, 2, 3][..], v2);
+        assert_ne!(v2, [1, 2]);
+    });
+}
+
+#[test]
+fn slice_into_iter() {
+    model(|| {
+        let (monitor, _) = new_monitored_arc();
+        let v1: Arc<[DropMonitor]> = vec![monitor.clone(); 3].into();
+        let v2 = v1.clone();
+        // Shared: elements are cloned.
+        assert_eq!(v1.into_iter().count(), 3);
+        // Unique: elements are moved, and the rest dropped with the iterator.
+        let mut iter = v2.into_iter();
+        let last = iter.next_back().unwrap();
+        assert_eq!(iter.len(), 2);
+        drop(iter);
+        drop(last);
+        assert!(monitor.is_unique());
+
+        let v1: Arc<[DropMonitor]> = vec![monitor.clone(); 2].into();
+        let w1 = Arc::downgrade(&v1);
+        let items = v1.into_iter().collect::<Vec<_>>();
+        assert!(w1.upgrade().is_none());
+        drop(items);
+        assert!(monitor.is_unique());
+
+        // Moved strings keep their buffers, and cloned ones get new buffers.
+        let v1: Arc<[String]> = vec!["a".to_owned(), "b".to_owned()].into();
+        let bufs = v1.iter().map(|s| s.as_ptr()).collect::<Vec<_>>();
+        let v2 = v1.clone();
+        let cloned = v1.into_iter().collect::<Vec<_>>();
+        assert_eq!(cloned, ["a", "b"]);
+        assert!(cloned.iter().zip(&bufs).all(|(s, &buf)| s.as_ptr() != buf));
+        let moved = v2.into_iter().rev().collect::<Vec<_>>();
+        assert_eq!(moved, ["b", "a"]);
+        assert!(moved
+            .iter()
+            .rev()
+            .zip(&bufs)
+            .all(|(s, &buf)| s.as_ptr() == buf));
+
+        let v1: Arc<[()]> = vec![(); 5].into();
+        assert_eq!(v1.into_iter().count(), 5);
+    });
+}
+
+#[test]
+fn slice_from_elem() {
+    model(|| {
+        let (monitor, _) = new_monitored_arc();
+        let v1 = Arc::from_elem(monitor.clone(), 3);
+        assert_eq!(v1.len(), 3);
+        assert!(Arc::<[DropMonitor]>::from_elem(monitor.clone(), 0).is_empty());
+        drop(v1);
+        assert!(monitor.is_unique());
+
+        assert_eq!(*Arc::repeat(7u8, 4), [7, 7, 7, 7]);
+        assert_eq!(*Arc::repeat((1u16, 2u32), 2), [(1, 2), (1, 2)]);
+    });
+}
+
+#[test]
+fn slice_from() {
+    model(|| {
+        let (monitor, _) = new_monitored_arc();
+        let items = [monitor.clone(), monitor.clone()];
+        let v1 = Arc::<[DropMonitor]>::from(&items[..]);
+        drop(items);
+        assert_eq!(v1.len(), 2);
+        drop(v1);
+        assert!(monitor.is_unique());
+
+        let v2: Arc<[i32]> = (1..4).filter(|i| i % 2 == 1).collect();
+        assert_eq!(*v2, [1, 3]);
+        assert!(core::iter::empty::<i32>().collect::<Arc<[_]>>().is_empty());
+        assert_eq!(&*Arc::<str>::from(String::from("shared")), "shared");
+        assert!(Arc::<str>::from(String::new()).is_empty());
+    });
+}
+
+#[test]
+fn slice_edit() {
+    model(|| {
+        let (monitor, _) = new_monitored_arc();
+        let mut v1: Arc<[DropMonitor]> = Arc::from_elem(monitor.clone(), 2);
+        Arc::push(&mut v1, monitor.clone());
+        let v2 = v1.clone();
+        Arc::truncate(&mut v1, 1);
+        assert_eq!((v1.len(), v2.len()), (1, 3));
+        drop(v2);
+        Arc::extend(&mut v1, [monitor.clone(), monitor.clone()]);
+        assert_eq!(v1.len(), 3);
+        drop(v1);
+        assert!(monitor.is_unique());
+
+        let mut v: Arc<[i32]> = Arc::from([1, 2, 3, 4]);
+        Arc::splice(&mut v, 1..3, [5, 6, 7]);
+        assert_eq!(*v, [1, 5, 6, 7, 4]);
+        Arc::truncate(&mut v, 0);
+        assert!(v.is_empty());
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn slice_splice_out_of_bounds() {
+    let mut v: Arc<[i32]> = Arc::from([1, 2, 3]);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        Arc::splice(&mut v, 2..4, [0]);
+    }));
+    assert!(res.is_err());
+    assert_eq!(*v, [1, 2, 3]);
+}
+
+// Clones of this panic, so edits must move the elements of a unique slice and leave a shared
+// one untouched.
+#[derive(Debug, PartialEq)]
+struct NoClone(i32);
+
+impl Clone for NoClone {
+    fn clone(&self) -> Self {
+        panic!("cloned");
+    }
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn slice_edit_in_place() {
+    let n = |xs: &[i32]| xs.iter().copied().map(NoClone).collect::<Vec<_>>();
+    let mut v: Arc<[NoClone]> = Arc::from(n(&[1, 2]));
+    Arc::push(&mut v, NoClone(3));
+    Arc::extend(&mut v, n(&[4, 5]));
+    Arc::splice(&mut v, 1..2, n(&[6, 7, 8]));
+    assert_eq!(*v, *n(&[1, 6, 7, 8, 3, 4, 5]));
+    Arc::splice(&mut v, 2..6, n(&[9]));
+    assert_eq!(*v, *n(&[1, 6, 9, 5]));
+    Arc::splice(&mut v, ..2, n(&[0, 0]));
+    Arc::truncate(&mut v, 3);
+    assert_eq!(*v, *n(&[0, 0, 9]));
+    for i in 0..10_000 {
+        Arc::push(&mut v, NoClone(i));
+    }
+    assert_eq!(v.len(), 10_003);
+
+    let shared = v.clone();
+    let edits: [fn(&mut Arc<[NoClone]>); 4] = [
+        |v| Arc::push(v, NoClone(0)),
+        |v| Arc::extend(v, []),
+        |v| Arc::splice(v, 1..2, []),
+        |v| Arc::truncate(v, 1),
+    ];
+    for edit in edits {
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| edit(&mut v)));
+        assert!(res.is_err());
+        assert!(Arc::ptr_eq(&v, &shared));
+    }
+
+    let (monitor, _) = new_monitored_arc();
+    let mut v: Arc<[DropMonitor]> = Arc::from_elem(monitor.clone(), 4);
+    Arc::splice(&mut v, 1..3, [monitor.clone()]);
+    Arc::splice(&mut v, 1..1, [monitor.clone(), monitor.clone()]);
+    Arc::truncate(&mut v, 2);
+    drop(v);
+    assert!(monitor.is_unique());
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn slice_truncate_panicking_drop() {
+    struct PanicOnDrop(bool);
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            if self.0 {
+                panic!("dropped");
+            }
+        }
+    }
+    impl Clone for PanicOnDrop {
+        fn clone(&self) -> Self {
+            Self(self.0)
+        }
+    }
+
+    let mut v: Arc<[PanicOnDrop]> = Arc::from(vec![
+        PanicOnDrop(false),
+        PanicOnDrop(true),
+        PanicOnDrop(false),
+    ]);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| Arc::truncate(&mut v, 1)));
+    assert!(res.is_err());
+    assert_eq!(v.len(), 1);
+}
+
+#[test]
+fn unique_slice_builder() {
+    use crate::UniqueArc;
+    model(|| {
+        let mut b = UniqueArc::<[i32]>::with_capacity(3);
+        b.push(1);
+        b.extend([2, 3]);
+        assert_eq!(b.try_push(4), Err(4));
+        b[0] = 0;
+        assert_eq!(*UniqueArc::into_arc(b), [0, 2, 3]);
+
+        let mut b = UniqueArc::<[i32]>::with_capacity(3);
+        b.push(1);
+        assert_eq!(*UniqueArc::into_arc(b), [1]);
+
+        let (monitor, _) = new_monitored_arc();
+        let mut b = UniqueArc::<[DropMonitor]>::with_capacity(2);
+        b.push(monitor.clone());
+        drop(b);
+        assert!(monitor.is_unique());
+    });
+}
+
+#[test]
+fn write_uninit() {
+    use core::mem::MaybeUninit;
+    model(|| {
+        let v1 = Arc::new(MaybeUninit::uninit());
+        assert_eq!(*Arc::write(v1, 42), 42);
+        let v1 = Arc::new(MaybeUninit::uninit());
+        let v2 = v1.clone();
+        assert_eq!(*Arc::write(v1, 42), 42);
+        drop(v2);
+
+        let uninit = || {
+            Arc::<[MaybeUninit<String>]>::from(vec![MaybeUninit::uninit(), MaybeUninit::uninit()])
+        };
+        let strs = ["a".to_owned(), "b".to_owned()];
+        assert_eq!(*Arc::write_slice_cloned(uninit(), &strs), strs);
+        assert_eq!(*Arc::write_filled(uninit(), "c".to_owned()), ["c", "c"]);
+        let v1 = uninit();
+        let v2 = v1.clone();
+        assert_eq!(*Arc::write_filled(v1, "c".to_owned()), ["c", "c"]);
+        drop(v2);
+    });
+}
+
+#[test]
+fn new_uninit_zeroed() {
+    use std::mem::MaybeUninit;
+    type Uninit<T> = Arc<MaybeUninit<T>>;
+    type UninitSlice<T> = Arc<[MaybeUninit<T>]>;
+    model(|| {
+        let mut v1 = Arc::<String>::new_uninit();
+        unsafe { Uninit::get_mut_unchecked(&mut v1).write("a".to_owned()) };
+        assert_eq!(*unsafe { Uninit::assume_init(v1) }, "a");
+        assert_eq!(*unsafe { Uninit::assume_init(Arc::<u64>::new_zeroed()) }, 0);
+
+        let mut v2 = Arc::<[String]>::new_uninit_slice(2);
+        for (i, elem) in unsafe { UninitSlice::get_mut_unchecked(&mut v2) }
+            .iter_mut()
+            .enumerate()
+        {
+            elem.write(i.to_string());
+        }
+        assert_eq!(*unsafe { UninitSlice::assume_init(v2) }, ["0", "1"]);
+        let v3 = Arc::<[u32]>::new_zeroed_slice(1000);
+        assert!(unsafe { UninitSlice::assume_init(v3) }
+            .iter()
+            .all(|&x| x == 0));
+        assert!(Arc::<[u8]>::new_zeroed_slice(0).is_empty());
+    });
+}
+
+#[test]
+fn try_new() {
+    use std::mem::MaybeUninit;
+    type Uninit<T> = Arc<MaybeUninit<T>>;
+    model(|| {
+        assert_eq!(*Arc::try_new(1).unwrap(), 1);
+        let v1 = Arc::<u64>::try_new_zeroed().unwrap();
+        assert_eq!(*unsafe { Uninit::assume_init(v1) }, 0);
+        let mut v2 = Arc::<u64>::try_new_uninit().unwrap();
+        unsafe { Uninit::get_mut_unchecked(&mut v2).write(2) };
+        assert_eq!(*unsafe { Uninit::assume_init(v2) }, 2);
+    });
+}
+
+// Loom tracks allocations itself, and does not expect them to fail.
+#[test]
+#[cfg(not(feature = "loom"))]
+fn try_new_failure() {
+    type Huge = [u8; 1 << 46];
+    assert_eq!(Arc::<Huge>::try_new_uninit().err(), Some(crate::AllocError));
+    assert_eq!(Arc::<Huge>::try_new_zeroed().err(), Some(crate::AllocError));
+}
+
+#[test]
+fn set_update() {
+    model(|| {
+        let mut v1 = Arc::new(1);
+        let addr = &*v1 as *const i32;
+        Arc::set(&mut v1, 2);
+        Arc::update(&mut v1, |x| x * 10);
+        assert_eq!(*v1, 20);
+        assert_eq!(&*v1 as *const i32, addr);
+
+        let v2 = v1.clone();
+        Arc::update(&mut v1, |x| x + 1);
+        assert_eq!((*v1, *v2), (21, 20));
+
+        let w2 = Arc::downgrade(&v2);
+        let mut v2 = v2;
+        Arc::set(&mut v2, 0);
+        assert!(w2.upgrade().is_none());
+    });
+}
+
+#[test]
+fn get_mut_make_mut() {
+    model(|| {
+        let mut v1 = Arc::new(1);
+        *Arc::get_mut(&mut v1).unwrap() += 1;
+        let v2 = v1.clone();
+        assert!(Arc::get_mut(&mut v1).is_none());
+        *Arc::make_mut(&mut v1) += 1;
+        assert_eq!((*v1, *v2), (3, 2));
+        assert_eq!(Arc::unwrap_or_clone(v2.clone()), 2);
+        assert_eq!(Arc::unwrap_or_clone(v2), 2);
+
+        let (monitor, mut v3) = new_monitored_arc();
+        let w3 = Arc::downgrade(&v3);
+        assert!(Arc::get_mut(&mut v3).is_none());
+        let addr = &*v3 as *const DropMonitor;
+        Arc::make_mut(&mut v3);
+        assert_ne!(&*v3 as *const DropMonitor, addr);
+        assert!(w3.upgrade().is_none());
+        let addr = &*v3 as *const DropMonitor;
+        Arc::make_mut(&mut v3);
+        assert_eq!(&*v3 as *const DropMonitor, addr);
+        drop(v3);
+        assert!(monitor.is_unique());
+
+        // Access comes back once weak references are gone.
+        let mut v4 = Arc::new(1);
+        drop(Arc::downgrade(&v4));
+        assert!(Arc::get_mut(&mut v4).is_some());
+        let addr = &*v4 as *const i32;
+        *Arc::make_mut(&mut v4) += 1;
+        Arc::set(&mut v4, 3);
+        assert_eq!(&*v4 as *const i32, addr);
+    });
+}
+
+#[test]
+fn trivial_upgrade() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let w1 = Arc::downgrade(&v1);
+        drop(w1.upgrade());
+        assert!(!monitor.is_unique());
+        drop(v1);
+        assert!(monitor.is_unique());
+        drop(w1);
+    });
+}
+
+#[test]
+fn clone_from() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let mut v2 = v1.clone();
+        v2.clone_from(&v1);
+        let mut w1 = Arc::downgrade(&v1);
+        w1.clone_from(&Arc::downgrade(&v2));
+        drop(v1);
+        assert!(!monitor.is_unique());
+
+        let (monitor2, v3) = new_monitored_arc();
+        v2.clone_from(&v3);
+        assert!(monitor.is_unique());
+        assert!(w1.upgrade().is_none());
+        w1.clone_from(&Arc::downgrade(&v3));
+        drop((v2, v3));
+        assert!(monitor2.is_unique());
+        assert!(w1.upgrade().is_none());
+    });
+}
+
+#[test]
+fn upgrade_after_drop() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let w1 = Arc::downgrade(&v1);
+        let w2 = w1.clone();
+        drop(v1);
+        assert!(monitor.is_unique());
+        assert!(w1.upgrade().is_none());
+        drop(w1);
+        assert!(w2.upgrade().is_none());
+    });
+}
+
+#[test]
+fn raw_pointers() {
+    use crate::align::{Align64, Aligned};
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let ptr = Arc::into_raw(v1);
+        unsafe {
+            assert!(!(*ptr).is_unique());
+            Arc::increment_strong_count(ptr);
+            Arc::decrement_strong_count(ptr);
+            let v1 = Arc::from_raw(ptr);
+            assert_eq!(Arc::as_ptr(&v1), ptr);
+
+            let w1 = Arc::downgrade(&v1).into_raw();
+            assert_eq!(w1, ptr);
+            drop(v1);
+            assert!(monitor.is_unique());
+            let w1 = Weak::from_raw(w1);
+            assert!(w1.upgrade().is_none());
+            let dangling = Weak::<u64>::new().into_raw();
+            assert!(Weak::from_raw(dangling).is_dangling());
+        }
+
+        let s: Arc<str> = Arc::from("raw");
+        let ptr = Arc::into_raw(s);
+        let s = unsafe { Arc::from_raw(ptr) };
+        assert_eq!(&*s, "raw");
+        let buf = Arc::<Aligned<Align64, [u16]>>::repeat_aligned(7, 3);
+        let ptr = Arc::into_raw(buf);
+        assert_eq!(ptr as *const u8 as usize % 64, 0);
+        let buf = unsafe { Arc::from_raw(ptr) };
+        assert_eq!(buf[..], [7; 3]);
+    });
+}
+
+#[test]
+fn into_weak() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let v2 = v1.clone();
+        let w1 = Arc::into_weak(v1);
+        assert!(w1.upgrade().is_some());
+        let w2 = Arc::into_weak(v2);
+        assert!(monitor.is_unique());
+        assert!(w1.upgrade().is_none());
+        drop((w1, w2));
+
+        let (monitor, v1) = new_monitored_arc();
+        let w1 = Arc::into_weak(v1);
+        assert!(monitor.is_unique());
+        assert!(w1.clone().upgrade().is_none());
+    });
+}
+
+#[test]
+fn upgrade_unchecked() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let w1 = Arc::downgrade(&v1);
+        let v2 = unsafe { w1.upgrade_unchecked() };
+        drop(v1);
+        assert!(w1.upgrade().is_some());
+        drop(v2);
+        assert!(monitor.is_unique());
+        assert!(w1.upgrade().is_none());
+    });
+}
+
+#[test]
+fn new_cyclic() {
+    struct Node {
+        this: Weak<Node>,
+        _monitor: DropMonitor,
+    }
+
+    model(|| {
+        let monitor = DropMonitor::default();
+        let v1 = Arc::new_cyclic(|this| {
+            assert!(this.upgrade().is_none());
+            Node {
+                this: this.clone(),
+                _monitor: monitor.clone(),
+            }
+        });
+        let v2 = v1.this.upgrade().unwrap();
+        drop(v1);
+        let w1 = Arc::downgrade(&v2);
+        drop(v2);
+        assert!(monitor.is_unique());
+        assert!(w1.upgrade().is_none());
+
+        let mut escaped = Weak::new();
+        let ret = Arc::<Node>::try_new_cyclic(|this| {
+            escaped = this.clone();
+            Err(())
+        });
+        assert!(ret.is_err());
+        assert!(escaped.upgrade().is_none());
+    });
+}
+
+#[test]
+fn soft_trim() {
+    use crate::{trim, SoftArc};
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let s1 = SoftArc::new(v1.clone());
+        let s2 = SoftArc::new(Arc::new(monitor.clone()));
+        drop(v1);
+        assert!(s1.get().is_some());
+        assert!(trim(Some(1)) >= 1);
+        assert!(s1.is_trimmed() && !s2.is_trimmed());
+        assert!(s1.get().is_none());
+        trim(None);
+        assert!(s2.get().is_none());
+        assert!(monitor.is_unique());
+    });
+}
+
+#[test]
+fn arc_cache() {
+    use crate::ArcCache;
+    model(|| {
+        let cache = ArcCache::new();
+        let v1 = cache.get_or_insert_with("a", || 1);
+        let v2 = cache.get_or_insert_with("a", || unreachable!());
+        assert!(std::ptr::eq(&*v1, &*v2));
+        drop(v2);
+        drop(v1);
+        assert!(cache.get("a").is_none());
+        assert_eq!(*cache.get_or_insert_with("a", || 2), 2);
+
+        let monitor = DropMonitor::default();
+        let cache = ArcCache::with_pinned(1);
+        cache.get_or_insert_with(1, || monitor.clone());
+        assert!(cache.get(&1).is_some());
+        cache.get_or_insert_with(2, || monitor.clone());
+        assert!(cache.get(&1).is_none() && cache.get(&2).is_some());
+        cache.clear();
+        assert!(monitor.is_unique());
+    });
+}
+
+// Values may use the cache when dropped, so pruning must never be left dropping one.
+#[test]
+fn arc_cache_prune_race() {
+    use crate::ArcCache;
+    use std::sync::Mutex;
+
+    struct Value(std::sync::Arc<Mutex<Option<thread::ThreadId>>>);
+    impl Drop for Value {
+        fn drop(&mut self) {
+            assert_ne!(*self.0.lock().unwrap(), Some(thread::current().id()));
+        }
+    }
+
+    model(|| {
+        let pruner = std::sync::Arc::new(Mutex::new(None));
+        let cache = ArcCache::new();
+        let value = cache.get_or_insert_with(0, || Value(pruner.clone()));
+        let t = thread::spawn(move || drop(value));
+        *pruner.lock().unwrap() = Some(thread::current().id());
+        cache.prune();
+        *pruner.lock().unwrap() = None;
+        t.join().unwrap();
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn singleflight() {
+    use crate::Singleflight;
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use std::sync::Barrier;
+    use std::task::{Context, Poll, Waker};
+
+    let flights = std::sync::Arc::new(Singleflight::new());
+    let runs = std::sync::Arc::new(AtomicUsize::new(0));
+    let barrier = std::sync::Arc::new(Barrier::new(4));
+    let threads = (0..4)
+        .map(|_| {
+            let (flights, runs, barrier) = (flights.clone(), runs.clone(), barrier.clone());
+            std::thread::spawn(move || {
+                barrier.wait();
+                flights.get_or_compute("a", || {
+                    runs.fetch_add(1, Relaxed);
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    42
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = threads
+        .into_iter()
+        .map(|t| t.join().unwrap())
+        .collect::<Vec<_>>();
+    assert!(results.iter().all(|v| **v == 42));
+    assert!(runs.load(Relaxed) < 4);
+    assert_eq!(*flights.get_or_compute("a", || 1), 1);
+
+    // A panicking leader hands over to a waiting caller.
+    let leader = std::thread::spawn({
+        let flights = flights.clone();
+        move || {
+            flights.get_or_compute("b", || {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                panic!("leader failed");
+            })
+        }
+    });
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    assert_eq!(*flights.get_or_compute("b", || 2), 2);
+    assert!(leader.join().is_err());
+
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut leader = Box::pin(flights.get_or_compute_async("c", std::future::pending));
+    assert!(leader.as_mut().poll(&mut cx).is_pending());
+    let mut follower = Box::pin(flights.get_or_compute_async("c", || async { 3 }));
+    assert!(follower.as_mut().poll(&mut cx).is_pending());
+    drop(leader);
+    assert!(matches!(follower.as_mut().poll(&mut cx), Poll::Ready(v) if *v == 3));
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn registry() {
+    use crate::registry;
+
+    struct Service {
+        id: u32,
+    }
+
+    assert!(registry::set(Arc::new(Service { id: 1 })).is_none());
+    assert_eq!(registry::get::<Service>().unwrap().id, 1);
+    {
+        let _scoped = registry::override_scoped(Arc::new(Service { id: 2 }));
+        assert_eq!(registry::get::<Service>().unwrap().id, 2);
+        let other = std::thread::spawn(|| registry::get::<Service>().unwrap().id);
+        assert_eq!(other.join().unwrap(), 1);
+    }
+    assert_eq!(registry::get::<Service>().unwrap().id, 1);
+    assert_eq!(registry::remove::<Service>().unwrap().id, 1);
+    assert!(registry::get::<Service>().is_none());
+
+    let value = Arc::new(Service { id: 3 });
+    registry::set_named("svc", Arc::from_any(value));
+    assert!(registry::get_named::<u32>("svc").is_none());
+    assert_eq!(registry::get_named::<Service>("svc").unwrap().id, 3);
+    let scoped = registry::override_named_scoped("svc", Arc::from_any(Arc::new(Service { id: 4 })));
+    assert_eq!(registry::get_named::<Service>("svc").unwrap().id, 4);
+    drop(scoped);
+    assert!(registry::remove_named("svc").is_some());
+    assert!(registry::get_named::<Service>("svc").is_none());
+}
+
+#[test]
+fn weak_list() {
+    use crate::list::{Node, WeakList};
+
+    fn values(list: &WeakList<i32>) -> Vec<i32> {
+        list.iter().map(|node| **node).collect()
+    }
+
+    model(|| {
+        let list = WeakList::new();
+        let (a, b, c) = (Node::new(1), Node::new(2), Node::new(3));
+        list.push_back(&b);
+        list.push_front(&a);
+        list.push_back(&c);
+        assert_eq!(values(&list), [1, 2, 3]);
+        assert_eq!(**b.next().unwrap(), 3);
+
+        // Dropped nodes unlink themselves, even from another thread.
+        thread::spawn(move || drop(b)).join().unwrap();
+        assert_eq!(values(&list), [1, 3]);
+        assert_eq!(list.len(), 2);
+        assert_eq!(**a.next().unwrap(), 3);
+        assert_eq!(**c.prev().unwrap(), 1);
+
+        let d = Node::new(4);
+        list.insert_after(&a, &d);
+        list.insert_before(&d, &Node::new(5));
+        assert_eq!(values(&list), [1, 4, 3]);
+        assert!(list.remove(&a) && !list.remove(&a));
+        list.push_back(&a);
+        assert_eq!(values(&list), [4, 3, 1]);
+        assert_eq!(**list.front().unwrap(), 4);
+        assert_eq!(**list.back().unwrap(), 1);
+
+        drop(list);
+        assert!(!a.is_linked() && a.next().is_none());
+        let other = WeakList::new();
+        other.push_back(&a);
+        assert_eq!(values(&other), [1]);
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn drop_iterative() {
+    struct Node {
+        next: Option<Arc<Node>>,
+        _monitor: DropMonitor,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            if let Some(next) = self.next.take() {
+                Arc::drop_iterative(next);
+            }
+        }
+    }
+
+    let monitor = DropMonitor::default();
+    // Deep enough to overflow a small stack if dropped recursively.
+    let list = (0..100_000).fold(None, |next, _| {
+        Some(Arc::new(Node {
+            next,
+            _monitor: monitor.clone(),
+        }))
+    });
+    let shared = list.as_ref().unwrap().next.clone().unwrap();
+    std::thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(move || drop(list))
+        .unwrap()
+        .join()
+        .unwrap();
+    assert!(!monitor.is_unique());
+    Arc::drop_iterative(shared);
+    assert!(monitor.is_unique());
+}
+
+#[test]
+// Loom threads share one OS thread, and thus the owning `ThreadId`.
+#[cfg(not(feature = "loom"))]
+fn hybrid_rc() {
+    use crate::hybrid::HybridRc;
+
+    model(|| {
+        let monitor = DropMonitor::default();
+        let local = HybridRc::new(monitor.clone());
+        let local2 = local.clone();
+        assert_eq!(HybridRc::local_count(&local), 2);
+        let shared = HybridRc::to_arc(&local);
+        drop((local, local2));
+        assert!(!monitor.is_unique());
+
+        let other = shared.clone();
+        let t = thread::spawn(move || other.into_local().is_err());
+        assert!(t.join().unwrap());
+        let local = shared.clone().into_local().unwrap();
+        assert_eq!(HybridRc::local_count(&local), 1);
+        let local2 = shared.into_local().unwrap();
+        assert!(HybridRc::ptr_eq(&local, &local2));
+        drop(local);
+        assert!(!monitor.is_unique());
+        drop(local2);
+        assert!(monitor.is_unique());
+    });
+}
+
+#[test]
+fn strong_arc() {
+    use crate::StrongArc;
+
+    model(|| {
+        let monitor = DropMonitor::default();
+        let strong = StrongArc::new(monitor.clone());
+        let other = strong.clone();
+        thread::spawn(move || drop(other)).join().unwrap();
+        assert_eq!(StrongArc::strong_count(&strong), 1);
+        let other = strong.clone();
+        let Err(strong) = StrongArc::try_into_arc(strong) else {
+            panic!("converted a shared StrongArc");
+        };
+        drop(other);
+        let arc = StrongArc::try_into_arc(strong).unwrap();
+        let weak = Arc::downgrade(&arc);
+        drop(arc);
+        assert!(monitor.is_unique() && weak.upgrade().is_none());
+
+        let strong = StrongArc::new(1);
+        let other = strong.clone();
+        assert_eq!(*StrongArc::into_arc(strong), 1);
+        assert_eq!(StrongArc::try_unwrap(other).ok(), Some(1));
+    });
+}
+
+#[test]
+fn arc_mutex() {
+    use crate::{ArcMutex, ArcRwLock};
+    model(|| {
+        let m1 = ArcMutex::new(1);
+        let w1 = m1.downgrade();
+        *w1.upgrade().unwrap().lock() += 1;
+        let m2 = m1.clone();
+        let m1 = m1.try_unwrap().unwrap_err();
+        drop(m2);
+        assert_eq!(m1.try_unwrap().unwrap(), 2);
+        assert!(w1.upgrade().is_none());
+
+        let l1 = ArcRwLock::new(vec![1]);
+        l1.clone().write().push(2);
+        assert_eq!(*l1.read(), [1, 2]);
+        assert_eq!(l1.try_unwrap().unwrap(), [1, 2]);
+    });
+}
+
+#[test]
+fn watch_channel() {
+    use core::future::Future;
+    use core::task::{Context, Poll, Waker};
+    model(|| {
+        let (tx, mut rx1) = crate::watch::channel(Arc::new(1));
+        assert!(!rx1.has_changed());
+        let mut rx2 = tx.subscribe();
+        tx.send(Arc::new(2));
+        assert!(rx1.has_changed());
+        assert_eq!(*rx1.get_and_update(), 2);
+        assert!(!rx1.has_changed());
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut changed = core::pin::pin!(rx1.changed());
+        assert!(changed.as_mut().poll(&mut cx).is_pending());
+        tx.send(Arc::new(3));
+        assert!(matches!(changed.poll(&mut cx), Poll::Ready(Ok(v)) if *v == 3));
+
+        drop(tx);
+        assert_eq!(*rx2.wait().unwrap(), 3);
+        assert!(rx2.wait().is_err());
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn watch_dropped_changed_unregister() {
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use std::sync::Arc as StdArc;
+    use std::task::{Context, Wake};
+
+    struct Counter(AtomicUsize);
+    impl Wake for Counter {
+        fn wake(self: StdArc<Self>) {
+            self.0.fetch_add(1, Relaxed);
+        }
+    }
+
+    let counter = StdArc::new(Counter(AtomicUsize::new(0)));
+    let waker = counter.clone().into();
+    let mut cx = Context::from_waker(&waker);
+    let (tx, mut rx) = crate::watch::channel(Arc::new(0));
+    for _ in 0..1000 {
+        let mut changed = std::pin::pin!(rx.changed());
+        assert!(changed.as_mut().poll(&mut cx).is_pending());
+    }
+    // One for `counter` and one for `waker`.
+    assert_eq!(StdArc::strong_count(&counter), 2);
+
+    // A slot from before a send is not ours to clear anymore.
+    let mut changed = Box::pin(rx.changed());
+    assert!(changed.as_mut().poll(&mut cx).is_pending());
+    tx.send(Arc::new(1));
+    assert_eq!(counter.0.load(Relaxed), 1);
+    let mut rx2 = tx.subscribe();
+    let mut changed2 = std::pin::pin!(rx2.changed());
+    assert!(changed2.as_mut().poll(&mut cx).is_pending());
+    drop(changed);
+    drop(tx);
+    assert_eq!(counter.0.load(Relaxed), 2);
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn watch_blocking() {
+    let (tx, mut rx) = crate::watch::channel(Arc::new(0));
+    let reader = thread::spawn(move || {
+        let mut last = 0;
+        while let Ok(v) = rx.wait() {
+            assert!(*v > last);
+            last = *v;
+        }
+        last
+    });
+    for i in 1..=100 {
+        tx.send(Arc::new(i));
+    }
+    drop(tx);
+    assert_eq!(reader.join().unwrap(), 100);
+}
+
+#[test]
+#[cfg(feature = "epoch")]
+fn release_deferred() {
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let w1 = Arc::downgrade(&v1);
+        for _ in 0..10 {
+            Arc::release_deferred(v1.clone());
+        }
+        Arc::release_deferred(v1);
+        assert!(w1.upgrade().is_some());
+        crate::epoch::flush();
+        assert!(w1.upgrade().is_none());
+        assert!(monitor.is_unique());
+
+        // A full batch is applied right away.
+        let (monitor, v1) = new_monitored_arc();
+        for _ in 0..100 {
+            Arc::release_deferred(v1.clone());
+        }
+        drop(v1);
+        crate::epoch::flush();
+        assert!(monitor.is_unique());
+    });
+}
+
+#[test]
+fn shared_ptr() {
+    use crate::SharedPtr;
+
+    fn check<P: SharedPtr<i32>>() {
+        let p1 = P::new(1);
+        let w1 = P::downgrade(&p1);
+        let p2 = P::upgrade(&w1).unwrap();
+        let p1 = P::try_unwrap(p1).unwrap_err();
+        drop(p2);
+        assert_eq!(P::try_unwrap(p1).ok(), Some(1));
+        assert!(P::upgrade(&w1).is_none());
+    }
+
+    model(|| {
+        check::<Arc<i32>>();
+        check::<std::sync::Arc<i32>>();
+        check::<std::rc::Rc<i32>>();
+    });
+}
+
+#[test]
+fn arc_cursor() {
+    use crate::ArcCursor;
+    use std::io::{BufRead, Read, Seek, SeekFrom};
+    model(|| {
+        let data: Arc<[u8]> = b"hello\nworld".to_vec().into();
+        let mut c1 = ArcCursor::new(data);
+        let mut c2 = c1.clone();
+        let mut line = String::new();
+        c1.read_line(&mut line).unwrap();
+        assert_eq!(line, "hello\n");
+        let mut rest = String::new();
+        c1.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "world");
+
+        assert_eq!(c2.seek(SeekFrom::End(-5)).unwrap(), 6);
+        let mut buf = [0; 5];
+        c2.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"world");
+        assert!(c2.read_exact(&mut buf).is_err());
+        assert!(c2.seek(SeekFrom::Current(-20)).is_err());
+    });
+}
+
+#[test]
+fn pod_cast() {
+    model(|| {
+        let bytes: Arc<[u8]> = vec![1, 0, 0, 0, 2, 0, 0, 0].into();
+        let words = Arc::try_cast_slice::<u32>(bytes).ok().unwrap();
+        assert_eq!(*words, [u32::from_le(1), u32::from_le(2)]);
+        let bytes = Arc::try_into_bytes(words).ok().unwrap();
+        assert_eq!(*bytes, [1, 0, 0, 0, 2, 0, 0, 0]);
+        let pair = Arc::try_cast::<[u16; 4]>(bytes).ok().unwrap();
+        assert_eq!(pair[0], u16::from_ne_bytes([1, 0]));
+
+        let odd: Arc<[u8]> = vec![0; 7].into();
+        let Err(odd) = Arc::try_cast_slice::<u32>(odd) else {
+            panic!("cast of a partial element");
+        };
+        assert!(Arc::try_cast::<u64>(odd).is_err());
+    });
+}
+
+#[test]
+#[cfg(unix)]
+fn as_fd() {
+    use std::os::fd::{AsFd, AsRawFd};
+    model(|| {
+        let stdout = Arc::new(std::io::stdout());
+        assert_eq!(stdout.as_raw_fd(), 1);
+        assert_eq!(stdout.as_fd().as_raw_fd(), 1);
+    });
+}
+
+#[test]
+#[cfg(all(unix, feature = "mmap", not(feature = "loom")))]
+fn mmap_file() {
+    use std::io::Write;
+    let path = std::env::temp_dir().join(format!("wfwrc-mmap-{}", std::process::id()));
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.write_all(b"mapped").unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+    let map = unsafe { Arc::map_file(&file) }.unwrap();
+    let cloned = map.clone();
+    assert!(thread::spawn(move || **cloned == *b"mapped")
+        .join()
+        .unwrap());
+    drop(map);
+
+    let empty = std::fs::File::create(&path).unwrap();
+    assert!(unsafe { Arc::map_file(&empty) }.unwrap().is_empty());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+#[cfg(all(unix, feature = "mlock", not(feature = "loom")))]
+fn locked_secret() {
+    let secret = Arc::new_locked([7u8; 32]).unwrap();
+    let cloned = secret.clone();
+    assert!(thread::spawn(move || **cloned == [7; 32]).join().unwrap());
+    drop(secret);
+
+    let (monitor, arc) = new_monitored_arc();
+    let locked = crate::secure::Locked::new(arc).unwrap();
+    assert!(!monitor.is_unique());
+    drop(locked);
+    assert!(monitor.is_unique());
+    assert!(crate::secure::Locked::new(()).is_ok());
+}
+
+#[test]
+fn aligned() {
+    use crate::align::{Align4096, Align64, Aligned};
+    use core::alloc::Layout;
+    model(|| {
+        let v = Arc::new_aligned::<Align64>(1u8);
+        assert_eq!(&*v as *const _ as usize % 64, 0);
+        assert_eq!(**v, 1);
+
+        let buf = Arc::<Aligned<Align4096, [u16]>>::repeat_aligned(7, 100);
+        assert_eq!(buf.as_ptr() as usize % 4096, 0);
+        assert_eq!(buf.len(), 100);
+        assert!(buf.iter().all(|&x| x == 7));
+        let inner = unsafe { buf.0.as_ref() };
+        assert_eq!(Arc::allocation_layout(&buf), Layout::for_value(inner));
+        assert_eq!(Aligned::<Align4096, [u16]>::ALIGN, 4096);
+    });
+}
+
+#[test]
+#[cfg(feature = "raw")]
+fn raw_primitives() {
+    use crate::raw;
+    use core::alloc::Layout;
+    model(|| {
+        let (monitor, _) = new_monitored_arc();
+        let ptr = unsafe {
+            let ptr =
+                raw::allocate::<DropMonitor>(Layout::new::<raw::ArcInner<DropMonitor>>(), |mem| {
+                    mem.cast()
+                });
+            raw::value_ptr(ptr).write(monitor.clone());
+            raw::acquire_strong_from_strong(ptr);
+            raw::acquire_weak_from_strong(ptr);
+            ptr
+        };
+        let v1 = unsafe { raw::arc_from_ptr(ptr) };
+        let w1 = unsafe { raw::weak_from_ptr(ptr) };
+        unsafe { raw::release_strong(ptr) };
+        assert!(w1.upgrade().is_some());
+        drop(v1);
+        assert!(monitor.is_unique());
+        let ptr = raw::weak_into_ptr(w1).unwrap();
+        assert!(!unsafe { raw::acquire_strong_from_weak(ptr) });
+        unsafe { raw::release_weak(ptr) };
+    });
+}
+
+#[test]
+#[cfg(all(feature = "diagnostics", not(feature = "loom")))]
+fn upgrade_diagnostic() {
+    let v1 = Arc::new(1);
+    let w1 = Arc::downgrade(&v1);
+    assert!(w1.upgrade_diagnostic().is_ok());
+    thread::Builder::new()
+        .name("dropper".into())
+        .spawn(move || drop(v1))
+        .unwrap()
+        .join()
+        .unwrap();
+    let err = w1.upgrade_diagnostic().unwrap_err();
+    assert_eq!(err.record().unwrap().thread_name(), Some("dropper"));
+    assert!(err.to_string().contains("dropper"));
+    assert!(crate::Weak::<i32>::new()
+        .upgrade_diagnostic()
+        .unwrap_err()
+        .record()
+        .is_none());
+}
+
+// Nothing could look at a record without weak references, so none is allocated.
+#[test]
+#[cfg(all(feature = "diagnostics", not(feature = "loom")))]
+fn drop_record_needs_weak() {
+    use crate::test_util::assert_no_alloc_or_block;
+
+    let v1 = Arc::<[u8]>::from([1, 2]);
+    let iter = assert_no_alloc_or_block(|| v1.into_iter());
+    drop(iter);
+
+    let w1 = Arc::downgrade(&Arc::new(1));
+    assert!(w1.upgrade_diagnostic().unwrap_err().record().is_some());
+    let w2 = Arc::into_weak(Arc::new(2));
+    assert!(w2.upgrade_diagnostic().unwrap_err().record().is_some());
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn no_leaks() {
+    use live_alloc::live_allocations;
+
+    let churn = || {
+        let v1 = Arc::new(1u32);
+        let w1 = Arc::downgrade(&v1);
+        drop(v1.clone());
+        drop(v1);
+        assert!(w1.upgrade().is_none());
+        drop(Arc::new(2u32));
+    };
+    // Lazily initialized runtime state, such as whether to capture backtraces, is not a leak.
+    churn();
+    let before = live_allocations();
+    for _ in 0..100 {
+        churn();
+    }
+    assert_eq!(live_allocations(), before);
+}
+
+#[test]
+#[cfg(feature = "diagnostics")]
+fn labels() {
+    model(|| {
+        let v1 = Arc::new_labeled("session-cache", 1);
+        assert_eq!(Arc::label(&v1), Some("session-cache"));
+        assert!(format!("{v1:?}").contains("label: \"session-cache\""));
+        assert_eq!(Arc::label(&Arc::new(1)), None);
+        let w1 = Arc::downgrade(&v1);
+        drop(v1);
+        let err = w1.upgrade_diagnostic().unwrap_err();
+        assert_eq!(err.label(), Some("session-cache"));
+        assert!(err.to_string().contains("[session-cache]"));
+    });
+}
 
-#[cfg(not(feature = "loom"))]
-use std::{sync, thread};
+#[test]
+#[cfg(all(feature = "diagnostics", not(feature = "loom")))]
+fn affinity_checked() {
+    use crate::diag::AffinityChecked;
 
-#[cfg(not(feature = "loom"))]
-pub fn model<F: Fn() + Sync + Send + 'static>(f: F) {
-    const ROUNDS: usize = 1_000;
-    for _ in 0..ROUNDS {
-        f();
+    let local = AffinityChecked::new(Arc::new(1));
+    let clone = local.clone();
+    assert_eq!(*clone, 1);
+    let result = std::thread::spawn(move || *clone).join();
+    let msg = *result.unwrap_err().downcast::<String>().unwrap();
+    assert!(msg.contains("used on thread"));
+    let moved = local.clone();
+    assert!(std::thread::spawn(move || drop(moved.clone()))
+        .join()
+        .is_err());
+    assert_eq!(*local.into_inner(), 1);
+}
+
+#[test]
+#[cfg(feature = "unstable-coerce")]
+fn unsized_coercion() {
+    trait Shape {
+        fn area(&self) -> u32;
+    }
+
+    struct Square {
+        side: u32,
+    }
+
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.side * self.side
+        }
     }
+
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let v2: Arc<dyn std::fmt::Debug + Send + Sync> = v1;
+        let w2: Weak<dyn std::fmt::Debug + Send + Sync> = Arc::downgrade(&v2);
+        drop(v2);
+        assert!(monitor.is_unique() && w2.upgrade().is_none());
+        let dangling: Weak<dyn std::fmt::Debug> = Weak::<i32>::new();
+        assert!(dangling.is_dangling());
+
+        let shape: Arc<dyn Shape> = Arc::new(Square { side: 3 });
+        assert_eq!(shape.area(), 9);
+    });
+}
+
+#[test]
+fn const_weak() {
+    static DANGLING: Weak<i32> = Weak::new();
+    const _: () = assert!(DANGLING.is_dangling());
+    model(|| {
+        let v1 = Arc::new(1);
+        let w1 = Arc::downgrade(&v1);
+        assert!(!w1.is_dangling());
+        assert!(w1.ptr_eq(&w1.clone()));
+        assert!(!w1.ptr_eq(&DANGLING));
+        assert!(DANGLING.ptr_eq(&Weak::new()));
+    });
+}
+
+#[test]
+fn pointer_identity() {
+    model(|| {
+        let (v1, v2) = (Arc::new(()), Arc::new(()));
+        assert!(Arc::ptr_eq(&v1, &v1.clone()));
+        assert!(!Arc::ptr_eq(&v1, &v2));
+
+        let w1 = Arc::downgrade(&v1);
+        assert_eq!(format!("{v1:p}"), format!("{:p}", Arc::as_ptr(&v1)));
+        assert_eq!(format!("{w1:p}"), format!("{v1:p}"));
+        assert_eq!(
+            format!("{:p}", Weak::<()>::new()),
+            format!("{:p}", usize::MAX as *const ())
+        );
+    });
+}
+
+#[test]
+fn arc_macro() {
+    model(|| {
+        let v: Arc<[i32]> = arc![1, 2, 3];
+        assert_eq!(*v, [1, 2, 3]);
+        let v: Arc<[u8]> = arc![1,];
+        assert_eq!(*v, [1]);
+        assert_eq!(*arc![1], [1]);
+        assert_eq!(*arc![2.5], [2.5]);
+        assert_eq!(*arc!['a'], ['a']);
+        assert_eq!(*arc![b"ab"], [b"ab"]);
+        let v: Arc<[u8]> = arc![7];
+        assert_eq!(*v, [7]);
+        let s = arc!["hello"];
+        assert_eq!(&*s, "hello");
+        assert_eq!(&*arc![""], "");
+        let v: Arc<[String]> = arc![String::from("x"); 2];
+        assert_eq!(*v, ["x", "x"]);
+        let v: Arc<[String]> = arc![];
+        assert!(v.is_empty());
+        let (monitor, _) = new_monitored_arc();
+        drop(arc![monitor.clone(), monitor.clone()]);
+        assert!(monitor.is_unique());
+    });
 }
 
-#[derive(Debug, Default, Clone)]
-struct DropMonitor(sync::Arc<()>);
+#[test]
+fn format_arc() {
+    use core::cell::Cell;
+    use core::fmt;
 
-impl DropMonitor {
-    fn is_unique(&self) -> bool {
-        sync::Arc::strong_count(&self.0) == 1
+    // Formats differently every time.
+    struct Growing(Cell<usize>);
+    impl fmt::Display for Growing {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.set(self.0.get() + 1);
+            write!(f, "{}", "x".repeat(self.0.get()))
+        }
     }
+
+    model(|| {
+        let s = format_arc!("{}-{:03}", "a", 7);
+        assert_eq!(&*s, "a-007");
+        assert_eq!(
+            Arc::heap_size(&s),
+            Arc::heap_size(&Arc::<str>::from("a-007"))
+        );
+        assert_eq!(&*format_arc!("plain"), "plain");
+        let empty = format_arc!("{}", "");
+        assert!(empty.is_empty());
+        #[cfg(not(feature = "loom"))]
+        assert!(Arc::ptr_eq(&empty, &Arc::<str>::default()));
+        // Measured, mismatched, then formatted again into a `String`.
+        assert_eq!(&*format_arc!("{}", Growing(Cell::new(0))), "xxx");
+    });
 }
 
-fn new_monitored_arc() -> (DropMonitor, Arc<DropMonitor>) {
+#[test]
+fn arc_or_weak() {
+    use crate::ArcOrWeak;
+    model(|| {
+        let (monitor, v1) = new_monitored_arc();
+        let mut e1 = ArcOrWeak::from(v1);
+        let mut e2 = e1.clone();
+        assert!(e1.get().is_some());
+        e1.make_weak();
+        assert!(!e1.is_strong() && e1.get().is_none());
+        assert!(e1.make_strong());
+        e1.make_weak();
+        e2.make_weak();
+        assert!(monitor.is_unique());
+        assert!(e1.upgrade().is_none());
+        assert!(!e2.make_strong());
+    });
+}
+
+#[test]
+fn drop_all() {
+    model(|| {
+        let (m1, v1) = new_monitored_arc();
+        let (m2, v2) = new_monitored_arc();
+        let w1 = Arc::downgrade(&v1);
+        let mut arcs = vec![v1.clone(), v2.clone(), v1.clone(), v2];
+        arcs.extend((0..5).map(|_| v1.clone()));
+        crate::drop_all(arcs);
+        assert!(m2.is_unique());
+        assert!(w1.upgrade().is_some());
+        crate::drop_all([v1]);
+        assert!(m1.is_unique());
+        crate::drop_all(Vec::<Arc<[u8]>>::new());
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn drop_all_panic() {
+    struct Item {
+        _monitor: DropMonitor,
+        panic: bool,
+    }
+    impl Drop for Item {
+        fn drop(&mut self) {
+            assert!(!self.panic, "drop");
+        }
+    }
+
     let monitor = DropMonitor::default();
-    let arc = Arc::new(monitor.clone());
-    (monitor, arc)
+    let item = |panic| Item {
+        _monitor: monitor.clone(),
+        panic,
+    };
+    let v1 = Arc::new(item(false));
+    let v2 = Arc::new(item(true));
+    let v3 = Arc::new(item(false));
+    let arcs = vec![v1.clone(), v2, v1, v3];
+    let ret = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| crate::drop_all(arcs)));
+    assert!(ret.is_err());
+    assert!(monitor.is_unique());
 }
 
 #[test]
-fn trivial_drop() {
+fn counts_with_ordering() {
+    use core::sync::atomic::Ordering::{Acquire, Relaxed, SeqCst};
     model(|| {
-        let (monitor, v1) = new_monitored_arc();
-        assert!(!monitor.is_unique());
+        let v1 = Arc::new(());
+        assert_eq!(Arc::strong_count_with(&v1, Relaxed), 1);
+        assert_eq!(Arc::weak_count_with(&v1, Relaxed), 0);
+        let v2 = v1.clone();
+        let w1 = Arc::downgrade(&v1);
+        let w2 = w1.clone();
+        assert_eq!(Arc::strong_count_with(&v1, Acquire), 2);
+        assert_eq!(Arc::weak_count_with(&v1, SeqCst), 2);
+        drop((v2, w1, w2));
+        assert_eq!(Arc::strong_count_with(&v1, Acquire), 1);
+        assert_eq!(Arc::weak_count_with(&v1, Acquire), 0);
+    });
+}
+
+#[test]
+fn counts() {
+    model(|| {
+        let v1 = Arc::new(());
+        assert!(Arc::is_unique(&v1));
+        let v2 = v1.clone();
+        let w1 = Arc::downgrade(&v1);
+        assert_eq!((Arc::strong_count(&v1), Arc::weak_count(&v1)), (2, 1));
+        assert!(!Arc::is_unique(&v1));
+        drop(v2);
+        assert!(!Arc::is_unique(&v1));
+        drop(w1);
+        assert_eq!((Arc::strong_count(&v1), Arc::weak_count(&v1)), (1, 0));
+        assert!(Arc::is_unique(&v1));
+    });
+}
+
+// The other thread always holds a strong or a weak reference while trading one for the other.
+#[test]
+fn is_unique_race() {
+    model(|| {
+        let v1 = Arc::new(0);
+        drop(Arc::downgrade(&v1));
+        let v2 = v1.clone();
+        let t = thread::spawn(move || {
+            let w2 = Arc::downgrade(&v2);
+            drop(v2);
+            w2.upgrade().unwrap()
+        });
+        assert!(!Arc::is_unique(&v1));
+        drop(t.join().unwrap());
+        assert!(Arc::is_unique(&v1));
+    });
+}
+
+#[test]
+fn debug_state() {
+    model(|| {
+        let v1 = Arc::new(());
+        let state = Arc::debug_state(&v1);
+        let implicit = usize::from(cfg!(feature = "cas-loop"));
+        assert_eq!(
+            (state.strong, state.weak, state.closed),
+            (1, implicit, false)
+        );
+        let w1 = Arc::downgrade(&v1);
+        let state = w1.debug_state().unwrap();
+        assert_eq!(state.strong, 1);
+        if cfg!(feature = "packed") {
+            assert_eq!(state.weak, 1);
+        } else if cfg!(feature = "cas-loop") {
+            assert_eq!(state.weak, 2);
+        } else {
+            assert!(state.weak_exist);
+            assert_eq!(state.weak, 2);
+        }
+        drop(v1);
+        assert!(w1.debug_state().unwrap().closed);
+        assert!(Weak::<()>::new().debug_state().is_none());
+    });
+}
+
+#[test]
+fn upgrade_or_else() {
+    model(|| {
+        let v1 = Arc::new(1);
+        let w1 = Arc::downgrade(&v1);
+        assert!(std::ptr::eq(&*w1.upgrade_or_else(|| unreachable!()), &*v1));
         drop(v1);
+        assert_eq!(*w1.upgrade_or_else(|| Arc::new(2)), 2);
+        assert_eq!(*w1.upgrade_or_default(), 0);
+        assert_eq!(*Weak::<i32>::new().upgrade_or_default(), 0);
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn assert_unique() {
+    let v1 = Arc::new(1);
+    Arc::assert_unique(&v1);
+    let v2 = v1.clone();
+    assert!(std::panic::catch_unwind(|| Arc::assert_unique(&v2)).is_err());
+    drop(v2);
+    Arc::assert_unique(&v1);
+    let w1 = Arc::downgrade(&v1);
+    let msg = std::panic::catch_unwind(|| Arc::assert_unique(&v1)).unwrap_err();
+    assert!(msg.downcast_ref::<String>().unwrap().contains("strong: 1"));
+    drop(w1);
+    Arc::assert_unique(&v1);
+}
+
+#[test]
+#[cfg(all(feature = "lock", not(feature = "loom")))]
+fn integrated_lock() {
+    use crate::lock::Locked;
+
+    let counter = Arc::new(Locked::new(0));
+    let guard = Arc::lock(&counter);
+    assert!(Arc::try_lock(&counter).is_none());
+    let weak = Arc::downgrade(&counter);
+    assert_eq!(
+        Arc::weak_count_with(&counter, core::sync::atomic::Ordering::Relaxed),
+        1
+    );
+    drop(guard);
+    let threads = (0..4)
+        .map(|_| {
+            let counter = weak.upgrade().unwrap();
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    *Arc::lock(&counter) += 1;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    threads.into_iter().for_each(|t| t.join().unwrap());
+    assert_eq!(*Arc::try_lock(&counter).unwrap(), 4000);
+    assert_eq!(Arc::debug_state(&counter).weak, 2);
+}
+
+#[test]
+fn error_downcast() {
+    use std::error::Error;
+
+    model(|| {
+        let err = Arc::<dyn Error + Send + Sync>::from_error(std::fmt::Error);
+        let err2 = err.clone();
+        assert_eq!(
+            err.to_string(),
+            "an error occurred when formatting an argument"
+        );
+        let Err(err) = err.downcast::<std::io::Error>() else {
+            panic!("downcast to the wrong type")
+        };
+        let err = err.downcast::<std::fmt::Error>().ok().unwrap();
+        assert_eq!(*err, std::fmt::Error);
+        drop(err2);
+        assert_eq!(
+            Arc::strong_count_with(&err, core::sync::atomic::Ordering::Relaxed),
+            1
+        );
+    });
+}
+
+#[test]
+fn guarded_future() {
+    use crate::task::guarded;
+    use std::future::{pending, ready, Future};
+    use std::task::{Context, Poll, Waker};
+
+    model(|| {
+        let mut cx = Context::from_waker(Waker::noop());
+        let (monitor, owner) = new_monitored_arc();
+        let mut done = guarded(Arc::downgrade(&owner), ready(1));
+        assert_eq!(
+            std::pin::Pin::new(&mut done).poll(&mut cx),
+            Poll::Ready(Some(1))
+        );
+
+        let mut task = Box::pin(guarded(Arc::downgrade(&owner), pending::<()>()));
+        assert!(task.as_mut().poll(&mut cx).is_pending());
+        thread::spawn(move || drop(owner)).join().unwrap();
         assert!(monitor.is_unique());
+        assert!(task.is_orphaned());
+        assert_eq!(task.as_mut().poll(&mut cx), Poll::Ready(None));
     });
 }
 
 #[test]
-fn trivial_clone() {
+fn shared_error() {
+    use crate::SharedError;
+    use std::error::Error;
+
+    #[derive(Debug)]
+    struct Outer(std::fmt::Error);
+
+    impl std::fmt::Display for Outer {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("outer")
+        }
+    }
+
+    impl Error for Outer {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
     model(|| {
-        let (monitor, v1) = new_monitored_arc();
-        let v2 = v1.clone();
-        let v3 = v2.clone();
+        let err = SharedError::from(Outer(std::fmt::Error));
+        let err2 = err.clone();
+        let t = thread::spawn(move || err2.to_string());
+        assert_eq!(t.join().unwrap(), "outer");
+        assert_eq!(format!("{err:?}"), "Outer(Error)");
+        assert!(err.source().unwrap().is::<std::fmt::Error>());
+        assert!(err.downcast_ref::<Outer>().is_some());
+        let err = Arc::<dyn Error + Send + Sync>::from(err);
+        assert!(err.downcast::<Outer>().is_ok());
+    });
+}
+
+#[test]
+fn pin_weak() {
+    use crate::PinWeak;
+
+    model(|| {
+        let monitor = DropMonitor::default();
+        let v1 = Arc::pin(monitor.clone());
+        let w1 = PinWeak::downgrade(&v1);
+        let v2 = w1.clone().upgrade().unwrap();
+        assert!(std::ptr::eq(&*v1, &*v2));
+        let t = thread::spawn(move || drop(v2));
         drop(v1);
-        drop(v2);
-        assert!(!monitor.is_unique());
-        drop(v3);
+        t.join().unwrap();
+        assert!(w1.upgrade().is_none());
         assert!(monitor.is_unique());
+        assert!(PinWeak::<()>::new().upgrade().is_none());
     });
 }
 
 #[test]
-fn trivial_upgrade() {
+#[cfg(not(feature = "loom"))]
+fn wait_group() {
+    use crate::wait_group::WaitGroup;
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use std::task::{Context, Poll, Waker};
+
+    let done = std::sync::Arc::new(AtomicUsize::new(0));
+    let group = WaitGroup::new();
+    for _ in 0..4 {
+        let (group, done) = (group.clone(), done.clone());
+        std::thread::spawn(move || {
+            done.fetch_add(1, Relaxed);
+            drop(group);
+        });
+    }
+    group.wait();
+    assert_eq!(done.load(Relaxed), 4);
+
+    let group = WaitGroup::new();
+    let other = group.clone();
+    let mut wait = group.wait_async();
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(std::pin::Pin::new(&mut wait).poll(&mut cx).is_pending());
+    drop(other);
+    assert_eq!(std::pin::Pin::new(&mut wait).poll(&mut cx), Poll::Ready(()));
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn dropped_waits_unregister() {
+    use crate::cancel::DropGuard;
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+    use std::sync::Arc as StdArc;
+    use std::task::{Context, Wake};
+
+    struct Counter(AtomicUsize);
+    impl Wake for Counter {
+        fn wake(self: StdArc<Self>) {
+            self.0.fetch_add(1, Relaxed);
+        }
+    }
+
+    // Like a `select!` loop recreating the future every iteration.
+    let counter = StdArc::new(Counter(AtomicUsize::new(0)));
+    let waker = counter.clone().into();
+    let mut cx = Context::from_waker(&waker);
+    let guard = DropGuard::new();
+    let token = guard.token();
+    for _ in 0..1000 {
+        let mut cancelled = token.cancelled();
+        assert!(std::pin::Pin::new(&mut cancelled)
+            .poll(&mut cx)
+            .is_pending());
+    }
+    let mut kept = token.cancelled();
+    assert!(std::pin::Pin::new(&mut kept).poll(&mut cx).is_pending());
+    // One for `counter`, one for `waker`, and one registered by `kept`.
+    assert_eq!(StdArc::strong_count(&counter), 3);
+    drop(guard);
+    // Only the waiter still around is woken.
+    assert_eq!(counter.0.load(Relaxed), 1);
+    assert!(std::pin::Pin::new(&mut kept).poll(&mut cx).is_ready());
+    drop(kept);
+    assert_eq!(StdArc::strong_count(&counter), 2);
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn cancellation_token() {
+    use crate::cancel::{CancellationToken, DropGuard};
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    let guard = DropGuard::new();
+    let other = guard.clone();
+    let token = guard.token();
+    assert!(!token.is_cancelled());
+    drop(guard);
+    assert!(!token.is_cancelled());
+    let t = std::thread::spawn({
+        let token = token.clone();
+        move || token.wait()
+    });
+    let mut cancelled = token.cancelled();
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(std::pin::Pin::new(&mut cancelled)
+        .poll(&mut cx)
+        .is_pending());
+    other.cancel();
+    assert!(token.is_cancelled());
+    assert_eq!(
+        std::pin::Pin::new(&mut cancelled).poll(&mut cx),
+        Poll::Ready(())
+    );
+    t.join().unwrap();
+    assert!(CancellationToken::cancelled_token().is_cancelled());
+}
+
+#[test]
+fn new_cyclic_group() {
+    struct Node {
+        peers: Vec<Weak<Node>>,
+        _monitor: DropMonitor,
+    }
+
     model(|| {
-        let (monitor, v1) = new_monitored_arc();
-        let w1 = Arc::downgrade(&v1);
-        drop(w1.upgrade());
+        let monitor = DropMonitor::default();
+        let (a, b) = Arc::new_cyclic2(|a: &Weak<i32>, b: &Weak<(Weak<i32>, i32)>| {
+            assert!(a.upgrade().is_none() && b.upgrade().is_none());
+            (1, (a.clone(), 2))
+        });
+        assert!(std::ptr::eq(&*(*b).0.upgrade().unwrap(), &*a));
+
+        let nodes = Arc::new_cyclic_group(|weaks: &[Weak<Node>; 3]| {
+            assert!(weaks.iter().all(|w| w.upgrade().is_none()));
+            core::array::from_fn(|i| Node {
+                peers: weaks.iter().skip(i + 1).cloned().collect(),
+                _monitor: monitor.clone(),
+            })
+        });
+        let first = nodes[0].peers[1].upgrade().unwrap();
+        assert!(std::ptr::eq(&*first, &*nodes[2]));
+        let t = thread::spawn(move || drop(nodes));
+        drop(first);
+        t.join().unwrap();
+        assert!(monitor.is_unique());
+    });
+}
+
+#[test]
+fn try_map() {
+    enum Shape {
+        Circle(f64),
+        Named(String, DropMonitor),
+    }
+
+    model(|| {
+        let monitor = DropMonitor::default();
+        let shape = Arc::new(Shape::Named("box".into(), monitor.clone()));
+        let shape = Arc::try_map(shape, |shape| match shape {
+            Shape::Circle(r) => Some(r),
+            Shape::Named(..) => None,
+        })
+        .err()
+        .unwrap();
+        let name = Arc::try_map(shape, |shape| match shape {
+            Shape::Named(name, monitor) => {
+                assert!(!monitor.is_unique());
+                Some(name.as_str())
+            }
+            Shape::Circle(_) => None,
+        })
+        .ok()
+        .unwrap();
+        assert_eq!(&*name, "box");
+        let first = crate::ProjectedArc::try_map_part(name, |name| name.get(..1)).unwrap();
+        let t = thread::spawn(move || assert_eq!(&*first, "b"));
+        t.join().unwrap();
+        assert!(monitor.is_unique());
+        assert!(matches!(
+            *Arc::try_map(Arc::new(Shape::Circle(1.0)), |_| None::<&()>)
+                .err()
+                .unwrap(),
+            Shape::Circle(_)
+        ));
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn object_pool() {
+    use crate::pool::Pool;
+
+    let pool = Pool::<Vec<u8>>::new(1);
+    let mut buf = pool.take();
+    buf.extend_from_slice(b"abc");
+    let buf = Arc::new(buf);
+    let buf2 = pool.get_or_else(|| Vec::with_capacity(16));
+    let ptr = buf.as_ptr();
+    let other = buf.clone();
+    drop(buf);
+    assert_eq!(pool.idle(), 0);
+    drop(other);
+    assert_eq!(pool.idle(), 1);
+    // Over capacity.
+    drop(buf2);
+    assert_eq!(pool.idle(), 1);
+
+    let reused = pool.get();
+    assert!(reused.is_empty() && reused.as_ptr() == ptr);
+    drop(pool);
+    drop(reused);
+}
+
+#[test]
+fn element_arcs() {
+    model(|| {
+        let monitor = DropMonitor::default();
+        let slice = Arc::<[DropMonitor]>::from(vec![monitor.clone(), monitor.clone()]);
+        assert!(Arc::get_arc(&slice, 2).is_none());
+        let first = Arc::get_arc(&slice, 0).unwrap();
+        let handles = Arc::element_arcs(&slice).collect::<Vec<_>>();
+        assert_eq!(handles.len(), 2);
+        assert!(std::ptr::eq(&*handles[0], &slice[0]) && std::ptr::eq(&*handles[1], &slice[1]));
+        assert!(crate::ProjectedArc::owner_eq(&first, &handles[1]));
+        drop(slice);
+
+        let t = thread::spawn(move || drop(handles));
+        t.join().unwrap();
         assert!(!monitor.is_unique());
-        drop(v1);
+        drop(first.clone());
+        drop(first);
         assert!(monitor.is_unique());
-        drop(w1);
     });
 }
 
@@ -87,6 +1960,29 @@ fn clone_clone() {
     });
 }
 
+#[test]
+fn try_unwrap_into_inner() {
+    model(|| {
+        let v1 = Arc::new(1);
+        let w1 = Arc::downgrade(&v1);
+        let v2 = v1.clone();
+        let v1 = Arc::try_unwrap(v1).unwrap_err();
+        assert_eq!(Arc::into_inner(v2), None);
+        assert_eq!(Arc::try_unwrap(v1), Ok(1));
+        assert!(w1.upgrade().is_none());
+
+        let (monitor, v1) = new_monitored_arc();
+        let w1 = Arc::downgrade(&v1);
+        let v2 = v1.clone();
+        let t = thread::spawn(move || Arc::into_inner(v2));
+        let values = [Arc::into_inner(v1), t.join().unwrap()];
+        assert_eq!(values.iter().flatten().count(), 1);
+        assert!(w1.upgrade().is_none());
+        drop(values);
+        assert!(monitor.is_unique());
+    });
+}
+
 #[test]
 fn clone_drop_upgrade() {
     model(|| {
@@ -140,3 +2036,141 @@ fn upgrade_upgrade() {
         assert!(monitor.is_unique());
     });
 }
+
+#[test]
+#[cfg(not(feature = "diagnostics"))]
+fn header_size() {
+    use core::mem::size_of;
+    let count = if cfg!(feature = "compact") {
+        4
+    } else {
+        size_of::<usize>()
+    };
+    let words = if cfg!(feature = "packed") { 1 } else { 2 };
+    assert_eq!(size_of::<crate::ArcInner<()>>(), words * count);
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn no_alloc_on_hot_paths() {
+    use crate::test_util::assert_no_alloc_or_block as assert_no_alloc;
+
+    let v1 = Arc::new(42);
+    let w1 = assert_no_alloc(|| {
+        let v2 = v1.clone();
+        let w1 = Arc::downgrade(&v2);
+        let w2 = Arc::downgrade(&v1);
+        drop(v2);
+        let w3 = w2.clone();
+        assert_eq!(w3.upgrade().as_deref(), Some(&42));
+        drop((w2, w3));
+        w1
+    });
+    drop(v1);
+    assert_no_alloc(|| assert!(w1.upgrade().is_none()));
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn blocking_is_caught() {
+    use crate::wait_group::WaitGroup;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let group = WaitGroup::new();
+    let other = group.clone();
+    let t = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        drop(other);
+    });
+    let checked = || crate::test_util::assert_no_alloc_or_block(|| group.wait());
+    let err = catch_unwind(AssertUnwindSafe(checked)).unwrap_err();
+    t.join().unwrap();
+    let msg = err.downcast::<String>().unwrap();
+    assert!(!msg.ends_with("blocking: 0"), "{msg}");
+}
+
+#[test]
+fn empty_slices() {
+    model(|| {
+        let v1 = Arc::<[String]>::default();
+        assert!(v1.is_empty());
+        let w1 = Arc::downgrade(&v1);
+        assert!(v1.clone().into_iter().next().is_none());
+        drop((v1, w1));
+        assert_eq!(&*Arc::<str>::default(), "");
+        assert!(Arc::<[u64]>::from(Vec::new()).is_empty());
+        assert!(Arc::repeat(0u8, 0).is_empty());
+        assert!(Arc::from_elem(String::new(), 0).is_empty());
+        // Over-aligned elements get a real allocation.
+        #[repr(align(128))]
+        struct Big;
+        assert!(Arc::<[Big]>::default().is_empty());
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn empty_slices_do_not_allocate() {
+    crate::test_util::assert_no_alloc_or_block(|| {
+        let v1 = Arc::<[String]>::default();
+        let v2 = Arc::<[u8]>::from(Vec::new());
+        let s1 = Arc::<str>::default();
+        drop((v1.clone(), v2, s1));
+        // The shared static is never released.
+        let w1 = Arc::downgrade(&v1);
+        drop(v1);
+        assert!(w1.upgrade().is_some());
+    });
+}
+
+#[test]
+#[cfg(not(feature = "loom"))]
+fn empty_slices_keep_their_count() {
+    let keep = Arc::<str>::default();
+    let before = Arc::debug_state(&keep);
+    for _ in 0..1000 {
+        drop(Arc::<str>::default());
+        drop(Arc::<[u64]>::default().clone());
+        drop(Arc::<[u8]>::from(Vec::new()));
+    }
+    assert_eq!(Arc::debug_state(&keep), before);
+}
+
+#[test]
+fn waker() {
+    use crate::task::{waker_ref, Wake};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Task {
+        wakes: AtomicUsize,
+        _monitor: DropMonitor,
+    }
+    impl Wake for Task {
+        fn wake(this: Arc<Self>) {
+            this.wakes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    model(|| {
+        let monitor = DropMonitor::default();
+        let task = Arc::new(Task {
+            wakes: AtomicUsize::new(0),
+            _monitor: monitor.clone(),
+        });
+
+        let owned = {
+            let borrowed = waker_ref(&task);
+            borrowed.wake_by_ref();
+            borrowed.clone()
+        };
+        owned.wake_by_ref();
+        owned.wake();
+
+        let waker = core::task::Waker::from(task.clone());
+        let cloned = waker.clone();
+        cloned.wake();
+        assert_eq!(task.wakes.load(Ordering::Relaxed), 4);
+        drop((task, waker));
+        assert!(monitor.is_unique());
+    });
+}
@@ -0,0 +1,148 @@
+use core::alloc::Layout;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ptr::{self, NonNull};
+
+use crate::{AllocError, Arc, ArcInner, UniqueArc};
+
+impl<T> Arc<T> {
+    /// Allocates room for a value, to be initialized in place before [`Arc::assume_init`].
+    pub fn new_uninit() -> Arc<MaybeUninit<T>> {
+        let layout = Layout::new::<ArcInner<MaybeUninit<T>>>();
+        Arc(unsafe { ArcInner::allocate(layout, |mem| mem.cast()) })
+    }
+
+    /// Like [`Arc::new_uninit`], but with the memory filled with zero bytes.
+    pub fn new_zeroed() -> Arc<MaybeUninit<T>> {
+        let layout = Layout::new::<ArcInner<MaybeUninit<T>>>();
+        Arc(unsafe { ArcInner::allocate_zeroed(layout, |mem| mem.cast()) })
+    }
+
+    /// Like [`Arc::new_uninit`], but returns an error instead of aborting if the allocation
+    /// fails.
+    pub fn try_new_uninit() -> Result<Arc<MaybeUninit<T>>, AllocError> {
+        let layout = Layout::new::<ArcInner<MaybeUninit<T>>>();
+        unsafe { ArcInner::try_allocate(layout, false, |mem| mem.cast()) }.map(Arc)
+    }
+
+    /// Like [`Arc::new_zeroed`], but returns an error instead of aborting if the allocation
+    /// fails.
+    pub fn try_new_zeroed() -> Result<Arc<MaybeUninit<T>>, AllocError> {
+        let layout = Layout::new::<ArcInner<MaybeUninit<T>>>();
+        unsafe { ArcInner::try_allocate(layout, true, |mem| mem.cast()) }.map(Arc)
+    }
+}
+
+impl<T> Arc<[T]> {
+    /// Allocates room for `len` elements, to be initialized in place before
+    /// [`Arc::assume_init`].
+    pub fn new_uninit_slice(len: usize) -> Arc<[MaybeUninit<T>]> {
+        Arc(ArcInner::allocate_for_slice(len))
+    }
+
+    /// Like [`Arc::new_uninit_slice`], but with the memory filled with zero bytes.
+    pub fn new_zeroed_slice(len: usize) -> Arc<[MaybeUninit<T>]> {
+        Arc(ArcInner::allocate_for_slice_zeroed(len))
+    }
+}
+
+// Associated functions like everywhere else, as `MaybeUninit` has an `assume_init` and `write`
+// of its own. The sized and slice forms share names, so callers may have to spell out which one,
+// as in `Arc::<[MaybeUninit<_>]>::assume_init(this)`.
+impl<T> Arc<MaybeUninit<T>> {
+    /// Converts to `Arc<T>`.
+    ///
+    /// # Safety
+    ///
+    /// The value must be initialized.
+    pub unsafe fn assume_init(this: Self) -> Arc<T> {
+        Arc(ManuallyDrop::new(this).0.cast())
+    }
+
+    /// Gives mutable access to the value, even if other references exist.
+    ///
+    /// # Safety
+    ///
+    /// No other handle may access the value while the returned reference is in use.
+    pub unsafe fn get_mut_unchecked(this: &mut Self) -> &mut MaybeUninit<T> {
+        // Only borrow the value: other handles may touch the counters concurrently.
+        &mut *ptr::addr_of_mut!((*this.0.as_ptr()).inner)
+    }
+
+    /// Initializes the value and converts to `Arc<T>`.
+    ///
+    /// The write happens in place if this is the only reference. Otherwise other handles keep
+    /// the uninitialized allocation and `value` is moved into a new one.
+    pub fn write(this: Self, value: T) -> Arc<T> {
+        if !unsafe { this.0.as_ref().is_unique() } {
+            return Arc::new(value);
+        }
+        let ptr = ManuallyDrop::new(this).0;
+        unsafe {
+            (*ptr.as_ptr()).inner.write(value);
+            Arc(ptr.cast())
+        }
+    }
+}
+
+impl<T> Arc<[MaybeUninit<T>]> {
+    /// Converts to `Arc<[T]>`.
+    ///
+    /// # Safety
+    ///
+    /// All elements must be initialized.
+    pub unsafe fn assume_init(this: Self) -> Arc<[T]> {
+        let ptr = ManuallyDrop::new(this).0.as_ptr() as *mut ArcInner<[T]>;
+        Arc(NonNull::new_unchecked(ptr))
+    }
+
+    /// Gives mutable access to the elements, even if other references exist.
+    ///
+    /// # Safety
+    ///
+    /// No other handle may access the elements while the returned reference is in use.
+    pub unsafe fn get_mut_unchecked(this: &mut Self) -> &mut [MaybeUninit<T>] {
+        // Only borrow the value: other handles may touch the counters concurrently.
+        &mut *ptr::addr_of_mut!((*this.0.as_ptr()).inner)
+    }
+
+    /// Initializes the slice with clones of `src` and converts to `Arc<[T]>`, in place if this
+    /// is the only reference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lengths differ.
+    pub fn write_slice_cloned(this: Self, src: &[T]) -> Arc<[T]>
+    where
+        T: Clone,
+    {
+        assert_eq!(this.len(), src.len(), "slice length mismatch");
+        Self::write_with(this, src.iter().cloned())
+    }
+
+    /// Initializes every element with clones of `value` and converts to `Arc<[T]>`, in place if
+    /// this is the only reference.
+    pub fn write_filled(this: Self, value: T) -> Arc<[T]>
+    where
+        T: Clone,
+    {
+        let len = this.len();
+        Self::write_with(this, core::iter::repeat_n(value, len))
+    }
+
+    fn write_with(this: Self, iter: impl ExactSizeIterator<Item = T>) -> Arc<[T]> {
+        debug_assert_eq!(this.len(), iter.len());
+        if !unsafe { this.0.as_ref().is_unique() } {
+            let mut slice = UniqueArc::with_capacity(iter.len());
+            slice.extend(iter);
+            return UniqueArc::into_arc(slice);
+        }
+        // A panic midway only leaks the written elements, which is fine for `MaybeUninit`.
+        let ptr = ManuallyDrop::new(this).0;
+        let elems = ArcInner::elements(ptr);
+        for (i, value) in iter.enumerate() {
+            unsafe { (*elems.add(i)).write(value) };
+        }
+        let ptr = ptr.as_ptr() as *mut ArcInner<[T]>;
+        Arc(unsafe { NonNull::new_unchecked(ptr) })
+    }
+}
@@ -0,0 +1,131 @@
+use core::mem::ManuallyDrop;
+use core::ptr::{self, NonNull};
+use core::{fmt, ops};
+
+use crate::{Arc, ArcInner};
+
+/// An `Arc` allocation that is known to be unique and may still be under construction.
+///
+/// For slices, it works as a fixed-capacity builder: elements are pushed into the final
+/// allocation one by one and [`UniqueArc::into_arc`] freezes the result without copying.
+/// Dropping an unfinished builder drops the elements pushed so far.
+pub struct UniqueArc<T: ?Sized> {
+    // The metadata of `init` covers the initialized part only, while `alloc` describes the whole
+    // allocation.
+    init: NonNull<ArcInner<T>>,
+    alloc: NonNull<ArcInner<T>>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for UniqueArc<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for UniqueArc<T> {}
+
+impl<T> UniqueArc<[T]> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let alloc = ArcInner::allocate_for_slice(capacity);
+        Self {
+            init: ArcInner::with_len(alloc, 0),
+            alloc,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        unsafe { self.alloc.as_ref().inner.len() }
+    }
+
+    /// Appends an element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is exhausted.
+    pub fn push(&mut self, value: T) {
+        if let Err(_value) = self.try_push(value) {
+            panic!("UniqueArc capacity exceeded");
+        }
+    }
+
+    /// Appends an element, or gives it back if the capacity is exhausted.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        let len = self.len();
+        if len == self.capacity() {
+            return Err(value);
+        }
+        unsafe { ArcInner::elements(self.alloc).add(len).write(value) };
+        self.init = ArcInner::with_len(self.alloc, len + 1);
+        Ok(())
+    }
+
+    /// Appends copies of all `items`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is exhausted.
+    pub fn extend_from_slice(&mut self, items: &[T])
+    where
+        T: Copy,
+    {
+        let len = self.len();
+        assert!(
+            items.len() <= self.capacity() - len,
+            "UniqueArc capacity exceeded"
+        );
+        unsafe {
+            let dst = ArcInner::elements(self.alloc).add(len);
+            ptr::copy_nonoverlapping(items.as_ptr(), dst, items.len());
+        }
+        self.init = ArcInner::with_len(self.alloc, len + items.len());
+    }
+
+    /// Freezes into a shared slice. This reuses the allocation if it is full, and moves the
+    /// elements into an exactly-sized one otherwise: an `Arc<[T]>` is freed with the layout of
+    /// its length, and the allocator must be given back the layout it allocated with.
+    ///
+    /// Fill the whole capacity to guarantee no reallocation.
+    pub fn into_arc(this: Self) -> Arc<[T]> {
+        let this = ManuallyDrop::new(this);
+        let len = this.len();
+        if len == this.capacity() {
+            return Arc(this.alloc);
+        }
+        let ptr = ArcInner::allocate_for_slice(len);
+        unsafe {
+            ptr::copy_nonoverlapping(ArcInner::elements(this.alloc), ArcInner::elements(ptr), len);
+            ArcInner::dealloc(this.alloc);
+        }
+        Arc(ptr)
+    }
+}
+
+impl<T> Extend<T> for UniqueArc<[T]> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|value| self.push(value));
+    }
+}
+
+impl<T: ?Sized> ops::Deref for UniqueArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &self.init.as_ref().inner }
+    }
+}
+
+impl<T: ?Sized> ops::DerefMut for UniqueArc<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut self.init.as_mut().inner }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for UniqueArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Drop for UniqueArc<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ManuallyDrop::drop(&mut self.init.as_mut().inner);
+            ArcInner::dealloc(self.alloc);
+        }
+    }
+}
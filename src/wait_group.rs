@@ -0,0 +1,171 @@
+//! Waiting for a group of participants to finish.
+//!
+//! The participants are the strong references of one [`Arc`], so the group is done exactly
+//! when its value is dropped, and joining or leaving costs a single counter update.
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, Thread};
+
+use crate::task::Wake;
+use crate::{Arc, Weak};
+
+/// Wakes its waiters when dropped.
+pub(crate) struct Signal {
+    // Slots of dropped waiters are emptied, and reused by new ones.
+    waiters: Mutex<Vec<Option<Waker>>>,
+}
+
+impl Signal {
+    pub(crate) fn new() -> Self {
+        Self {
+            waiters: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Drop for Signal {
+    fn drop(&mut self) {
+        let waiters = self
+            .waiters
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        mem::take(waiters)
+            .into_iter()
+            .flatten()
+            .for_each(Waker::wake);
+    }
+}
+
+/// A participant in a group. Cloning registers another participant, and dropping signals that
+/// it is done.
+#[derive(Clone)]
+pub struct WaitGroup(Arc<Signal>);
+
+impl WaitGroup {
+    pub fn new() -> Self {
+        Self(Arc::new(Signal::new()))
+    }
+
+    /// Leaves the group and blocks until all other participants are done.
+    pub fn wait(self) {
+        self.wait_async().block();
+    }
+
+    /// Leaves the group and returns a future that resolves once all other participants are
+    /// done.
+    pub fn wait_async(self) -> Wait {
+        Wait::new(Arc::into_weak(self.0))
+    }
+}
+
+impl Default for WaitGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitGroup")
+            .field(
+                "participants",
+                &Arc::strong_count_with(&self.0, core::sync::atomic::Ordering::Relaxed),
+            )
+            .finish()
+    }
+}
+
+/// The future returned by [`WaitGroup::wait_async`] and
+/// [`CancellationToken::cancelled`](crate::cancel::CancellationToken::cancelled).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Wait {
+    group: Weak<Signal>,
+    // Where our waker is registered, to replace it rather than piling up new ones.
+    slot: Option<usize>,
+}
+
+impl Wait {
+    /// Resolves once `signal` is dropped.
+    pub(crate) fn new(signal: Weak<Signal>) -> Self {
+        Self {
+            group: signal,
+            slot: None,
+        }
+    }
+
+    /// Blocks the current thread until resolved.
+    pub(crate) fn block(mut self) {
+        struct Unpark(Thread);
+
+        impl Wake for Unpark {
+            fn wake(this: Arc<Self>) {
+                (*this).0.unpark();
+            }
+        }
+
+        let waker = Arc::into_waker(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        while Pin::new(&mut self).poll(&mut cx).is_pending() {
+            crate::note_blocking();
+            thread::park();
+        }
+    }
+}
+
+impl Future for Wait {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        // Briefly joining the group is fine: if we turn out to be the last participant, our
+        // own drop below wakes us.
+        let Some(signal) = this.group.upgrade() else {
+            return Poll::Ready(());
+        };
+        let mut waiters = signal
+            .waiters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match this.slot {
+            Some(slot) => match &mut waiters[slot] {
+                Some(waker) => waker.clone_from(cx.waker()),
+                waker => *waker = Some(cx.waker().clone()),
+            },
+            None => match waiters.iter().position(Option::is_none) {
+                Some(slot) => {
+                    waiters[slot] = Some(cx.waker().clone());
+                    this.slot = Some(slot);
+                }
+                None => {
+                    this.slot = Some(waiters.len());
+                    waiters.push(Some(cx.waker().clone()));
+                }
+            },
+        }
+        drop(waiters);
+        drop(signal);
+        Poll::Pending
+    }
+}
+
+impl Drop for Wait {
+    fn drop(&mut self) {
+        let Some(slot) = self.slot else {
+            return;
+        };
+        // As in `poll`, dropping the upgraded reference may end the group, which locks the
+        // waiters again.
+        let Some(signal) = self.group.upgrade() else {
+            return;
+        };
+        signal
+            .waiters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)[slot] = None;
+    }
+}
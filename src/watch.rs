@@ -0,0 +1,233 @@
+//! A single-producer, multi-consumer channel that only keeps the latest value.
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+use crate::Arc;
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    changed: Condvar,
+}
+
+struct State<T> {
+    value: Arc<T>,
+    version: u64,
+    closed: bool,
+    // Emptied whenever the version changes or the channel closes. Slots of dropped futures are
+    // emptied, and reused by new ones.
+    wakers: Vec<Option<Waker>>,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn notify(&self, state: MutexGuard<'_, State<T>>, wakers: Vec<Option<Waker>>) {
+        drop(state);
+        self.changed.notify_all();
+        wakers.into_iter().flatten().for_each(Waker::wake);
+    }
+}
+
+/// Creates a channel whose receivers start out having seen `initial`.
+pub fn channel<T>(initial: Arc<T>) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            value: initial,
+            version: 0,
+            closed: false,
+            wakers: Vec::new(),
+        }),
+        changed: Condvar::new(),
+    });
+    let rx = Receiver {
+        shared: shared.clone(),
+        seen: 0,
+    };
+    (Sender { shared }, rx)
+}
+
+/// Returned when waiting for a change after the [`Sender`] is gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("watch channel closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Publishes a new snapshot and notifies all receivers. The previous snapshot is dropped
+    /// once no receiver holds it anymore.
+    pub fn send(&self, value: Arc<T>) {
+        let mut state = self.shared.lock();
+        let old = std::mem::replace(&mut state.value, value);
+        state.version += 1;
+        let wakers = std::mem::take(&mut state.wakers);
+        self.shared.notify(state, wakers);
+        drop(old);
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        self.shared.lock().value.clone()
+    }
+
+    pub fn subscribe(&self) -> Receiver<T> {
+        let state = self.shared.lock();
+        Receiver {
+            shared: self.shared.clone(),
+            seen: state.version,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.closed = true;
+        let wakers = std::mem::take(&mut state.wakers);
+        self.shared.notify(state, wakers);
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sender")
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    seen: u64,
+}
+
+impl<T> Receiver<T> {
+    /// Returns the latest snapshot without marking it as seen.
+    pub fn get(&self) -> Arc<T> {
+        self.shared.lock().value.clone()
+    }
+
+    /// Returns the latest snapshot and marks it as seen.
+    pub fn get_and_update(&mut self) -> Arc<T> {
+        let state = self.shared.lock();
+        self.seen = state.version;
+        state.value.clone()
+    }
+
+    pub fn has_changed(&self) -> bool {
+        self.shared.lock().version != self.seen
+    }
+
+    /// Blocks until a snapshot newer than the last seen one is sent, and returns it.
+    pub fn wait(&mut self) -> Result<Arc<T>, Closed> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(ret) = poll_state(&mut self.seen, &state) {
+                return ret;
+            }
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Like [`Receiver::wait`], but asynchronous.
+    pub fn changed(&mut self) -> Changed<'_, T> {
+        Changed {
+            rx: self,
+            slot: None,
+        }
+    }
+}
+
+fn poll_state<T>(seen: &mut u64, state: &State<T>) -> Option<Result<Arc<T>, Closed>> {
+    if state.version != *seen {
+        *seen = state.version;
+        Some(Ok(state.value.clone()))
+    } else if state.closed {
+        Some(Err(Closed))
+    } else {
+        None
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("seen", &self.seen)
+            .finish()
+    }
+}
+
+/// Future returned by [`Receiver::changed`].
+#[must_use = "futures do nothing unless polled"]
+pub struct Changed<'a, T> {
+    rx: &'a mut Receiver<T>,
+    // Where our waker is registered, and for which version, since sending empties the list.
+    slot: Option<(u64, usize)>,
+}
+
+impl<T> Future for Changed<'_, T> {
+    type Output = Result<Arc<T>, Closed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let rx = &mut *this.rx;
+        let mut state = rx.shared.lock();
+        if let Some(ret) = poll_state(&mut rx.seen, &state) {
+            return Poll::Ready(ret);
+        }
+        match this.slot {
+            Some((version, slot)) if version == state.version => match &mut state.wakers[slot] {
+                Some(waker) => waker.clone_from(cx.waker()),
+                waker => *waker = Some(cx.waker().clone()),
+            },
+            _ => {
+                let slot = match state.wakers.iter().position(Option::is_none) {
+                    Some(slot) => slot,
+                    None => {
+                        state.wakers.push(None);
+                        state.wakers.len() - 1
+                    }
+                };
+                state.wakers[slot] = Some(cx.waker().clone());
+                this.slot = Some((state.version, slot));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Changed<'_, T> {
+    fn drop(&mut self) {
+        let Some((version, slot)) = self.slot else {
+            return;
+        };
+        let mut state = self.rx.shared.lock();
+        if version == state.version && !state.closed {
+            state.wakers[slot] = None;
+        }
+    }
+}